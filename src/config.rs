@@ -0,0 +1,191 @@
+use super::profile::{Profile, ProfileError};
+use super::random::ContextBuilder;
+use super::range::{range_str, size_range_str};
+use super::rule::Rule;
+
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+    Profile(ProfileError),
+    UnknownPreset(String),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Cannot read config: {e}"),
+            Self::Toml(e) => write!(f, "Invalid config: {e}"),
+            Self::Json(e) => write!(f, "Invalid config: {e}"),
+            Self::Profile(e) => write!(f, "{e}"),
+            Self::UnknownPreset(name) => write!(
+                f,
+                "Unknown preset '{name}' (known presets are {})",
+                PRESETS.join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// All the options of a `ContextBuilder`.
+///
+/// Unset options keep the value of the preset (if any) or the builder default.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+    /// Version of the generation algorithm, see `GENERATOR_VERSION`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", with = "range_str")]
+    pub dir_depth: Option<Range<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none", with = "range_str")]
+    pub nb_dir_child: Option<Range<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none", with = "range_str")]
+    pub nb_file_child: Option<Range<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub binary_ratio: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dir_ratio: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none", with = "size_range_str")]
+    pub file_len: Option<Range<usize>>,
+    /// Path to a profile file, relative to the config file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<PathBuf>,
+    /// Per depth or per path overrides, written as `[[rule]]` tables in TOML.
+    #[serde(rename = "rule", skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<Rule>,
+}
+
+pub const PRESETS: &[&str] = &[
+    "tiny",
+    "many-small-files",
+    "few-huge-files",
+    "deep",
+    "wide",
+    "source-tree",
+];
+
+impl Config {
+    pub fn preset(name: &str) -> Result<Self, ConfigError> {
+        let config = match name {
+            "tiny" => Self {
+                dir_depth: Some(1..3),
+                nb_dir_child: Some(0..3),
+                nb_file_child: Some(0..4),
+                file_len: Some(10..1_000),
+                ..Default::default()
+            },
+            "many-small-files" => Self {
+                dir_depth: Some(3..5),
+                nb_dir_child: Some(1..6),
+                nb_file_child: Some(50..200),
+                file_len: Some(0..4_096),
+                ..Default::default()
+            },
+            "few-huge-files" => Self {
+                dir_depth: Some(0..2),
+                nb_dir_child: Some(0..2),
+                nb_file_child: Some(1..4),
+                binary_ratio: Some(0.8),
+                file_len: Some(100_000_000..1_000_000_000),
+                ..Default::default()
+            },
+            "deep" => Self {
+                dir_depth: Some(20..30),
+                nb_dir_child: Some(1..2),
+                nb_file_child: Some(0..3),
+                file_len: Some(10..10_000),
+                ..Default::default()
+            },
+            "wide" => Self {
+                dir_depth: Some(1..2),
+                nb_dir_child: Some(100..200),
+                nb_file_child: Some(100..500),
+                file_len: Some(10..10_000),
+                ..Default::default()
+            },
+            "source-tree" => Self {
+                dir_depth: Some(3..7),
+                nb_dir_child: Some(0..6),
+                nb_file_child: Some(0..15),
+                binary_ratio: Some(0.05),
+                file_len: Some(100..100_000),
+                ..Default::default()
+            },
+            _ => return Err(ConfigError::UnknownPreset(name.into())),
+        };
+        Ok(config)
+    }
+
+    /// Load a config file. Files with a `.json` extension are parsed as JSON,
+    /// all others as TOML.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(path)?;
+        let mut config: Self = if path.extension().is_some_and(|e| e == "json") {
+            serde_json::from_str(&content).map_err(ConfigError::Json)?
+        } else {
+            toml::from_str(&content).map_err(ConfigError::Toml)?
+        };
+        if let (Some(profile), Some(dir)) = (&config.profile, path.parent()) {
+            config.profile = Some(dir.join(profile));
+        }
+        Ok(config)
+    }
+
+    /// Set the options of `builder`. The preset is applied first, then the
+    /// options set in this config.
+    pub fn apply(&self, builder: &mut ContextBuilder) -> Result<(), ConfigError> {
+        if let Some(preset) = &self.preset {
+            Self::preset(preset)?.apply(builder)?;
+        }
+        if let Some(path) = &self.profile {
+            let mut input = std::io::BufReader::new(std::fs::File::open(path)?);
+            builder.profile(Profile::read(&mut input).map_err(ConfigError::Profile)?);
+        }
+        if let Some(v) = self.version {
+            builder.version(v);
+        }
+        if let Some(v) = self.seed {
+            builder.seed(v);
+        }
+        if let Some(v) = &self.dir_depth {
+            builder.dir_depth(v.clone());
+        }
+        if let Some(v) = &self.nb_dir_child {
+            builder.nb_dir_child(v.clone());
+        }
+        if let Some(v) = &self.nb_file_child {
+            builder.nb_file_child(v.clone());
+        }
+        if let Some(v) = self.binary_ratio {
+            builder.binary_ratio(v);
+        }
+        if let Some(v) = self.dir_ratio {
+            builder.dir_ratio(v);
+        }
+        if let Some(v) = &self.file_len {
+            builder.file_len(v.clone());
+        }
+        for rule in &self.rules {
+            builder.rule(rule.clone());
+        }
+        Ok(())
+    }
+}
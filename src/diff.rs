@@ -0,0 +1,108 @@
+use super::tree::{DirEntry, EntryRef, FileEntry};
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// A difference between two trees. Paths are relative to the root.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Difference {
+    /// Entry only in the new tree.
+    Added(PathBuf),
+    /// Entry only in the old tree.
+    Removed(PathBuf),
+    /// File in both trees with a different content.
+    Changed(PathBuf),
+}
+
+impl Difference {
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Added(p) | Self::Removed(p) | Self::Changed(p) => p,
+        }
+    }
+}
+
+impl std::fmt::Display for Difference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Added(path) => write!(f, "added\t{}", path.display()),
+            Self::Removed(path) => write!(f, "removed\t{}", path.display()),
+            Self::Changed(path) => write!(f, "changed\t{}", path.display()),
+        }
+    }
+}
+
+impl FileEntry {
+    fn same_content(&self, other: &FileEntry) -> bool {
+        self.size == other.size
+            && (self.size == 0
+                || (self.seed == other.seed
+                    && self.is_binary == other.is_binary
+                    && self.content_version() == other.content_version()))
+    }
+}
+
+impl DirEntry {
+    /// List the entries that differ from `self` (the old tree) to `new`.
+    ///
+    /// Every entry of an added or removed directory is listed. An entry being
+    /// a file in one tree and a directory in the other is removed and added.
+    /// Differences are sorted by path, a directory coming before its content.
+    pub fn diff(&self, new: &DirEntry) -> Vec<Difference> {
+        let mut differences = vec![];
+        diff_dir(Path::new(""), self, new, &mut differences);
+        differences
+    }
+}
+
+fn diff_dir(path: &Path, old: &DirEntry, new: &DirEntry, differences: &mut Vec<Difference>) {
+    let names = old
+        .files
+        .iter()
+        .map(|f| &f.name)
+        .chain(old.dirs.iter().map(|d| &d.name))
+        .chain(new.files.iter().map(|f| &f.name))
+        .chain(new.dirs.iter().map(|d| &d.name))
+        .collect::<BTreeSet<_>>();
+    for name in names {
+        let path = path.join(name);
+        match (old.get_child(name).ok(), new.get_child(name).ok()) {
+            (Some(EntryRef::File(old)), Some(EntryRef::File(new))) => {
+                if !old.same_content(new) {
+                    differences.push(Difference::Changed(path));
+                }
+            }
+            (Some(EntryRef::Dir(old)), Some(EntryRef::Dir(new))) => {
+                diff_dir(&path, old, new, differences);
+            }
+            (old, new) => {
+                if let Some(old) = old {
+                    list_all(&path, old, Difference::Removed, differences);
+                }
+                if let Some(new) = new {
+                    list_all(&path, new, Difference::Added, differences);
+                }
+            }
+        }
+    }
+}
+
+/// Push `entry` and all its content as `kind` differences.
+pub(crate) fn list_all(
+    path: &Path,
+    entry: EntryRef,
+    kind: fn(PathBuf) -> Difference,
+    differences: &mut Vec<Difference>,
+) {
+    differences.push(kind(path.to_path_buf()));
+    if let EntryRef::Dir(dir) = entry {
+        let mut content = vec![];
+        dir.walk(&mut |sub_path, _| {
+            content.push(kind(path.join(sub_path)));
+            Ok(())
+        })
+        .expect("Collecting paths cannot fail");
+        content.sort_by(|a, b| a.path().cmp(b.path()));
+        differences.extend(content);
+    }
+}
@@ -0,0 +1,124 @@
+use super::Exporter;
+use crate::tree::{DirEntry, FileEntry, GID, PERM, UID};
+
+use jbk::creator::{CompHint, ContentAdder, InputFile, InputReader};
+use libarx::create::{EntryKind, EntryTrait, SimpleCreator};
+use std::io::{Cursor, Error, ErrorKind, Read, Result};
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::Arc;
+
+// Jubako only takes content from memory or from a real file. Small files are
+// generated in memory, bigger ones in a temporary file.
+const IN_MEMORY_MAX: usize = 4 * 1024 * 1024;
+
+fn content_input(file: &FileEntry) -> Result<Box<dyn InputReader>> {
+    if file.size() <= IN_MEMORY_MAX {
+        let mut content = Vec::with_capacity(file.size());
+        file.get_reader().read_to_end(&mut content)?;
+        Ok(Box::new(Cursor::new(content)))
+    } else {
+        let mut tmp = tempfile::tempfile()?;
+        std::io::copy(&mut file.get_reader(), &mut tmp)?;
+        Ok(Box::new(InputFile::new(tmp)?))
+    }
+}
+
+struct ArxEntry {
+    path: libarx::PathBuf,
+    kind: EntryKind,
+}
+
+impl EntryTrait for ArxEntry {
+    fn kind(&self) -> std::result::Result<Option<EntryKind>, libarx::CreatorError> {
+        Ok(Some(self.kind.clone()))
+    }
+
+    fn path(&self) -> &libarx::Path {
+        &self.path
+    }
+
+    fn uid(&self) -> u64 {
+        UID.into()
+    }
+
+    fn gid(&self) -> u64 {
+        GID.into()
+    }
+
+    fn mode(&self) -> u64 {
+        PERM.into()
+    }
+
+    fn mtime(&self) -> u64 {
+        0
+    }
+}
+
+fn arx_path(path: &Path) -> Result<libarx::PathBuf> {
+    libarx::PathBuf::from_path(path).map_err(|e| Error::new(ErrorKind::InvalidInput, e))
+}
+
+/// Create an arx archive directly from the tree, without writing the files on disk.
+pub struct ArxWriter {
+    // None once finalized.
+    creator: Option<SimpleCreator>,
+}
+
+impl ArxWriter {
+    pub fn new(path: &Path) -> Result<Self> {
+        let path = jbk::Utf8Path::from_path(path).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("{path:?} is not a valid utf8 path"),
+            )
+        })?;
+        let creator = SimpleCreator::new(
+            path,
+            jbk::creator::ConcatMode::OneFile,
+            Arc::new(()),
+            Rc::new(()),
+            jbk::creator::Compression::default(),
+        )
+        .map_err(Error::other)?;
+        Ok(Self {
+            creator: Some(creator),
+        })
+    }
+
+    fn creator(&mut self) -> Result<&mut SimpleCreator> {
+        self.creator
+            .as_mut()
+            .ok_or_else(|| Error::other("Archive is already finalized"))
+    }
+}
+
+impl Exporter for ArxWriter {
+    fn add_dir(&mut self, path: &Path, _dir: &DirEntry) -> Result<()> {
+        let entry = ArxEntry {
+            path: arx_path(path)?,
+            kind: EntryKind::Dir,
+        };
+        self.creator()?.add_entry(&entry).map_err(Error::other)
+    }
+
+    fn add_file(&mut self, path: &Path, file: &FileEntry) -> Result<()> {
+        let path = arx_path(path)?;
+        let creator = self.creator()?;
+        let content = creator
+            .adder()
+            .add_content(content_input(file)?, CompHint::Detect)?;
+        let entry = ArxEntry {
+            path,
+            kind: EntryKind::File((file.size() as u64).into(), content),
+        };
+        creator.add_entry(&entry).map_err(Error::other)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        match self.creator.take() {
+            Some(creator) => creator.finalize().map_err(Error::other),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use super::{copy_content, Exporter};
+use crate::tree::{DirEntry, FileEntry, GID, PERM, UID};
+
+use std::io::{Error, ErrorKind, Result, Write};
+use std::path::Path;
+
+// SVR4 "newc" cpio format, without checksum.
+
+const MAGIC: &[u8] = b"070701";
+const HEADER_LEN: usize = 110;
+const TRAILER: &[u8] = b"TRAILER!!!";
+
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+
+pub struct CpioWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> CpioWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    fn write_header(
+        &mut self,
+        name: &[u8],
+        ino: u64,
+        mode: u32,
+        nlink: u32,
+        size: u64,
+    ) -> Result<()> {
+        let too_big = |what| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("{what} does not fit in a cpio archive"),
+            )
+        };
+        let ino = u32::try_from(ino).map_err(|_| too_big("Inode"))?;
+        let size = u32::try_from(size).map_err(|_| too_big("File size"))?;
+        let mut header = Vec::with_capacity(HEADER_LEN + name.len() + 4);
+        header.extend_from_slice(MAGIC);
+        let fields = [
+            ino,
+            mode,
+            UID,
+            GID,
+            nlink,
+            0, // mtime
+            size,
+            0, // devmajor
+            0, // devminor
+            0, // rdevmajor
+            0, // rdevminor
+            name.len() as u32 + 1,
+            0, // check
+        ];
+        for field in fields {
+            header.extend_from_slice(format!("{field:08X}").as_bytes());
+        }
+        header.extend_from_slice(name);
+        header.push(0);
+        header.resize(header.len().next_multiple_of(4), 0);
+        self.out.write_all(&header)
+    }
+}
+
+impl<W: Write> Exporter for CpioWriter<W> {
+    fn add_dir(&mut self, path: &Path, dir: &DirEntry) -> Result<()> {
+        let mode = S_IFDIR | PERM as u32;
+        self.write_header(path.as_os_str().as_encoded_bytes(), dir.ino, mode, 2, 0)
+    }
+
+    fn add_file(&mut self, path: &Path, file: &FileEntry) -> Result<()> {
+        let mode = S_IFREG | PERM as u32;
+        let size = file.size() as u64;
+        self.write_header(path.as_os_str().as_encoded_bytes(), file.ino, mode, 1, size)?;
+        copy_content(file, &mut self.out)?;
+        let padding = size.next_multiple_of(4) - size;
+        self.out.write_all(&[0; 4][..padding as usize])
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.write_header(TRAILER, 0, 0, 1, 0)?;
+        self.out.flush()
+    }
+}
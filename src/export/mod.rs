@@ -0,0 +1,59 @@
+#[cfg(feature = "arx")]
+mod arx;
+mod cpio;
+mod tar;
+mod zip;
+
+#[cfg(feature = "arx")]
+pub use self::arx::ArxWriter;
+pub use self::cpio::CpioWriter;
+pub use self::tar::TarWriter;
+pub use self::zip::{ZipMethod, ZipWriter};
+
+use super::tree::{DirEntry, EntryRef, FileEntry};
+use std::io::Result;
+use std::path::Path;
+
+/// An archive format the tree can be streamed to.
+///
+/// Paths are relative to the root of the tree. Directories are always added
+/// before their content.
+pub trait Exporter {
+    fn add_dir(&mut self, path: &Path, dir: &DirEntry) -> Result<()>;
+    fn add_file(&mut self, path: &Path, file: &FileEntry) -> Result<()>;
+    /// Write the end of the archive. Nothing can be added after it.
+    fn finish(&mut self) -> Result<()>;
+}
+
+impl DirEntry {
+    /// Stream the whole tree (but the root itself) to `exporter`, without
+    /// writing anything on disk.
+    pub fn export(&self, exporter: &mut dyn Exporter) -> Result<()> {
+        self.walk(&mut |path, entry| match entry {
+            EntryRef::Dir(d) => exporter.add_dir(path, d),
+            EntryRef::File(f) => exporter.add_file(path, f),
+        })?;
+        exporter.finish()
+    }
+}
+
+/// Copy the content of `file` to `out`, checking the reader produces exactly
+/// `file.size()` bytes.
+fn copy_content(file: &FileEntry, out: &mut dyn std::io::Write) -> Result<()> {
+    let written = std::io::copy(&mut file.get_reader(), out)?;
+    check_size(file, written)
+}
+
+fn check_size(file: &FileEntry, written: u64) -> Result<()> {
+    if written != file.size() as u64 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            format!(
+                "{:?} content is {written} bytes long, expected {}",
+                file.name,
+                file.size()
+            ),
+        ));
+    }
+    Ok(())
+}
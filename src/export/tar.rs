@@ -0,0 +1,131 @@
+use super::{copy_content, Exporter};
+use crate::tree::{DirEntry, FileEntry, GID, PERM, UID};
+
+use std::io::{Result, Write};
+use std::path::Path;
+
+// POSIX ustar format. Entries whose path or size do not fit in the ustar header
+// are preceded by a PAX extended header.
+
+const BLOCK_SIZE: usize = 512;
+const NAME_LEN: usize = 100;
+const PREFIX_LEN: usize = 155;
+// Size field is 11 octal digits.
+const MAX_USTAR_SIZE: u64 = 0o77777777777;
+
+const REGULAR: u8 = b'0';
+const DIRECTORY: u8 = b'5';
+const PAX_HEADER: u8 = b'x';
+
+pub struct TarWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> TarWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    fn write_entry(&mut self, path: &[u8], kind: u8, size: u64) -> Result<()> {
+        let split = split_path(path);
+        let mut records = Vec::new();
+        if split.is_none() {
+            add_pax_record(&mut records, "path", path);
+        }
+        if size > MAX_USTAR_SIZE {
+            add_pax_record(&mut records, "size", size.to_string().as_bytes());
+        }
+        if !records.is_empty() {
+            let mut pax_name = b"PaxHeaders/".to_vec();
+            pax_name.extend_from_slice(&path[path.len().saturating_sub(NAME_LEN - 11)..]);
+            let header = header(b"", &pax_name, PAX_HEADER, records.len() as u64);
+            self.out.write_all(&header)?;
+            self.out.write_all(&records)?;
+            self.pad(records.len() as u64)?;
+        }
+        let (prefix, name) = split.unwrap_or((b"", &path[..NAME_LEN.min(path.len())]));
+        let header_size = if size > MAX_USTAR_SIZE { 0 } else { size };
+        self.out.write_all(&header(prefix, name, kind, header_size))
+    }
+
+    fn pad(&mut self, size: u64) -> Result<()> {
+        let padding = (BLOCK_SIZE - (size as usize % BLOCK_SIZE)) % BLOCK_SIZE;
+        self.out.write_all(&[0; BLOCK_SIZE][..padding])
+    }
+}
+
+impl<W: Write> Exporter for TarWriter<W> {
+    fn add_dir(&mut self, path: &Path, _dir: &DirEntry) -> Result<()> {
+        let mut path = path.as_os_str().as_encoded_bytes().to_vec();
+        path.push(b'/');
+        self.write_entry(&path, DIRECTORY, 0)
+    }
+
+    fn add_file(&mut self, path: &Path, file: &FileEntry) -> Result<()> {
+        let size = file.size() as u64;
+        self.write_entry(path.as_os_str().as_encoded_bytes(), REGULAR, size)?;
+        copy_content(file, &mut self.out)?;
+        self.pad(size)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.out.write_all(&[0; 2 * BLOCK_SIZE])?;
+        self.out.flush()
+    }
+}
+
+/// Split `path` in a ustar (prefix, name) pair, if possible.
+fn split_path(path: &[u8]) -> Option<(&[u8], &[u8])> {
+    if path.len() <= NAME_LEN {
+        return Some((b"", path));
+    }
+    // Don't split on a trailing '/' as the name would be empty.
+    let searched = &path[..path.len() - 1];
+    searched
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| **c == b'/')
+        .map(|(idx, _)| (&path[..idx], &path[idx + 1..]))
+        .find(|(prefix, name)| prefix.len() <= PREFIX_LEN && name.len() <= NAME_LEN)
+}
+
+fn add_pax_record(records: &mut Vec<u8>, key: &str, value: &[u8]) {
+    // A record is "<len> <key>=<value>\n", where <len> includes its own digits.
+    let base_len = key.len() + value.len() + 3;
+    let mut len = base_len + 1;
+    while len != base_len + len.to_string().len() {
+        len = base_len + len.to_string().len();
+    }
+    records.extend_from_slice(format!("{len} {key}=").as_bytes());
+    records.extend_from_slice(value);
+    records.push(b'\n');
+}
+
+fn octal(field: &mut [u8], value: u64) {
+    let width = field.len() - 1;
+    let digits = format!("{value:0width$o}");
+    field[..width].copy_from_slice(digits.as_bytes());
+}
+
+fn header(prefix: &[u8], name: &[u8], kind: u8, size: u64) -> [u8; BLOCK_SIZE] {
+    let mut header = [0; BLOCK_SIZE];
+    header[..name.len()].copy_from_slice(name);
+    octal(&mut header[100..108], PERM.into());
+    octal(&mut header[108..116], UID.into());
+    octal(&mut header[116..124], GID.into());
+    octal(&mut header[124..136], size);
+    octal(&mut header[136..148], 0); // mtime
+    header[156] = kind;
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    header[345..345 + prefix.len()].copy_from_slice(prefix);
+    // The checksum is computed with the checksum field filled with spaces.
+    header[148..156].fill(b' ');
+    let checksum = header.iter().map(|b| *b as u64).sum::<u64>();
+    octal(&mut header[148..155], checksum);
+    header
+}
@@ -0,0 +1,321 @@
+use super::{check_size, Exporter};
+use crate::tree::{DirEntry, FileEntry, PERM};
+
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, CrcReader};
+use std::io::{Error, ErrorKind, Result, Write};
+use std::path::Path;
+
+// Zip archive written in one pass: file entries have their crc and sizes in a
+// data descriptor following the content. Zip64 extensions are used only when
+// needed.
+
+const LOCAL_HEADER_SIG: u32 = 0x04034b50;
+const DATA_DESCRIPTOR_SIG: u32 = 0x08074b50;
+const CENTRAL_HEADER_SIG: u32 = 0x02014b50;
+const ZIP64_END_SIG: u32 = 0x06064b50;
+const ZIP64_LOCATOR_SIG: u32 = 0x07064b50;
+const END_SIG: u32 = 0x06054b50;
+
+const VERSION_DEFAULT: u16 = 20;
+const VERSION_ZIP64: u16 = 45;
+// Attributes are unix ones.
+const MADE_BY_UNIX: u16 = 3 << 8;
+
+const FLAG_DATA_DESCRIPTOR: u16 = 1 << 3;
+const FLAG_UTF8: u16 = 1 << 11;
+
+// 1980-01-01 00:00:00, the zip epoch.
+const DOS_TIME: u16 = 0;
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+const DOS_DIRECTORY: u32 = 0x10;
+
+// Files which may not fit in 32 bits once compressed use zip64 local headers.
+const ZIP64_FILE_SIZE: u64 = 0xFFFF_0000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZipMethod {
+    Stored,
+    Deflate,
+}
+
+impl ZipMethod {
+    fn id(&self) -> u16 {
+        match self {
+            Self::Stored => 0,
+            Self::Deflate => 8,
+        }
+    }
+}
+
+impl std::str::FromStr for ZipMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "stored" => Ok(Self::Stored),
+            "deflate" => Ok(Self::Deflate),
+            _ => Err(format!("'{s}' is not a zip method (stored or deflate)")),
+        }
+    }
+}
+
+struct CentralEntry {
+    name: Vec<u8>,
+    flags: u16,
+    method: u16,
+    crc: u32,
+    compressed_size: u64,
+    size: u64,
+    offset: u64,
+    external_attr: u32,
+}
+
+struct CountingWriter<W: Write> {
+    inner: W,
+    count: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+}
+
+pub struct ZipWriter<W: Write> {
+    out: CountingWriter<W>,
+    method: ZipMethod,
+    entries: Vec<CentralEntry>,
+}
+
+fn name_flags(name: &[u8]) -> u16 {
+    if std::str::from_utf8(name).is_ok() {
+        FLAG_UTF8
+    } else {
+        0
+    }
+}
+
+impl<W: Write> ZipWriter<W> {
+    pub fn new(out: W, method: ZipMethod) -> Self {
+        Self {
+            out: CountingWriter {
+                inner: out,
+                count: 0,
+            },
+            method,
+            entries: vec![],
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out.inner
+    }
+
+    fn write_local_header(&mut self, entry: &CentralEntry, zip64: bool) -> Result<()> {
+        let mut header = Vec::with_capacity(30 + entry.name.len() + 20);
+        header.extend_from_slice(&LOCAL_HEADER_SIG.to_le_bytes());
+        let version = if zip64 {
+            VERSION_ZIP64
+        } else {
+            VERSION_DEFAULT
+        };
+        header.extend_from_slice(&version.to_le_bytes());
+        header.extend_from_slice(&entry.flags.to_le_bytes());
+        header.extend_from_slice(&entry.method.to_le_bytes());
+        header.extend_from_slice(&DOS_TIME.to_le_bytes());
+        header.extend_from_slice(&DOS_DATE.to_le_bytes());
+        // Crc and sizes are in the data descriptor (or zero for directories).
+        header.extend_from_slice(&0u32.to_le_bytes());
+        let size = if zip64 { u32::MAX } else { 0 };
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+        let extra_len: u16 = if zip64 { 20 } else { 0 };
+        header.extend_from_slice(&extra_len.to_le_bytes());
+        header.extend_from_slice(&entry.name);
+        if zip64 {
+            header.extend_from_slice(&1u16.to_le_bytes());
+            header.extend_from_slice(&16u16.to_le_bytes());
+            header.extend_from_slice(&[0; 16]);
+        }
+        self.out.write_all(&header)
+    }
+
+    fn write_central_directory(&mut self) -> Result<()> {
+        let start = self.out.count;
+        for entry in &self.entries {
+            let mut extra = Vec::new();
+            let mut field = |value: u64| {
+                if value >= u32::MAX as u64 {
+                    extra.extend_from_slice(&value.to_le_bytes());
+                    u32::MAX
+                } else {
+                    value as u32
+                }
+            };
+            let size = field(entry.size);
+            let compressed_size = field(entry.compressed_size);
+            let offset = field(entry.offset);
+            let version = if extra.is_empty() {
+                VERSION_DEFAULT
+            } else {
+                VERSION_ZIP64
+            };
+            let mut header = Vec::with_capacity(46 + entry.name.len() + 28);
+            header.extend_from_slice(&CENTRAL_HEADER_SIG.to_le_bytes());
+            header.extend_from_slice(&(MADE_BY_UNIX | version).to_le_bytes());
+            header.extend_from_slice(&version.to_le_bytes());
+            header.extend_from_slice(&entry.flags.to_le_bytes());
+            header.extend_from_slice(&entry.method.to_le_bytes());
+            header.extend_from_slice(&DOS_TIME.to_le_bytes());
+            header.extend_from_slice(&DOS_DATE.to_le_bytes());
+            header.extend_from_slice(&entry.crc.to_le_bytes());
+            header.extend_from_slice(&compressed_size.to_le_bytes());
+            header.extend_from_slice(&size.to_le_bytes());
+            header.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+            let extra_len = if extra.is_empty() { 0 } else { extra.len() + 4 };
+            header.extend_from_slice(&(extra_len as u16).to_le_bytes());
+            header.extend_from_slice(&0u16.to_le_bytes()); // comment length
+            header.extend_from_slice(&0u16.to_le_bytes()); // disk number
+            header.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+            header.extend_from_slice(&entry.external_attr.to_le_bytes());
+            header.extend_from_slice(&offset.to_le_bytes());
+            header.extend_from_slice(&entry.name);
+            if !extra.is_empty() {
+                header.extend_from_slice(&1u16.to_le_bytes());
+                header.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+                header.extend_from_slice(&extra);
+            }
+            self.out.write_all(&header)?;
+        }
+        let end = self.out.count;
+        self.write_end(start, end - start)
+    }
+
+    fn write_end(&mut self, cd_offset: u64, cd_size: u64) -> Result<()> {
+        let nb_entries = self.entries.len() as u64;
+        let zip64 = nb_entries >= u16::MAX as u64
+            || cd_offset >= u32::MAX as u64
+            || cd_size >= u32::MAX as u64;
+        let mut end = Vec::with_capacity(56 + 20 + 22);
+        if zip64 {
+            let zip64_end_offset = self.out.count;
+            end.extend_from_slice(&ZIP64_END_SIG.to_le_bytes());
+            end.extend_from_slice(&44u64.to_le_bytes()); // size of the remaining record
+            end.extend_from_slice(&(MADE_BY_UNIX | VERSION_ZIP64).to_le_bytes());
+            end.extend_from_slice(&VERSION_ZIP64.to_le_bytes());
+            end.extend_from_slice(&0u32.to_le_bytes()); // disk number
+            end.extend_from_slice(&0u32.to_le_bytes()); // disk of central directory
+            end.extend_from_slice(&nb_entries.to_le_bytes());
+            end.extend_from_slice(&nb_entries.to_le_bytes());
+            end.extend_from_slice(&cd_size.to_le_bytes());
+            end.extend_from_slice(&cd_offset.to_le_bytes());
+
+            end.extend_from_slice(&ZIP64_LOCATOR_SIG.to_le_bytes());
+            end.extend_from_slice(&0u32.to_le_bytes()); // disk of zip64 end record
+            end.extend_from_slice(&zip64_end_offset.to_le_bytes());
+            end.extend_from_slice(&1u32.to_le_bytes()); // number of disks
+        }
+        let nb_entries = nb_entries.min(u16::MAX as u64) as u16;
+        end.extend_from_slice(&END_SIG.to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes()); // disk number
+        end.extend_from_slice(&0u16.to_le_bytes()); // disk of central directory
+        end.extend_from_slice(&nb_entries.to_le_bytes());
+        end.extend_from_slice(&nb_entries.to_le_bytes());
+        end.extend_from_slice(&(cd_size.min(u32::MAX as u64) as u32).to_le_bytes());
+        end.extend_from_slice(&(cd_offset.min(u32::MAX as u64) as u32).to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        self.out.write_all(&end)
+    }
+}
+
+impl<W: Write> Exporter for ZipWriter<W> {
+    fn add_dir(&mut self, path: &Path, _dir: &DirEntry) -> Result<()> {
+        let mut name = path.as_os_str().as_encoded_bytes().to_vec();
+        name.push(b'/');
+        let entry = CentralEntry {
+            flags: name_flags(&name),
+            name,
+            method: ZipMethod::Stored.id(),
+            crc: 0,
+            compressed_size: 0,
+            size: 0,
+            offset: self.out.count,
+            external_attr: ((S_IFDIR | PERM as u32) << 16) | DOS_DIRECTORY,
+        };
+        self.write_local_header(&entry, false)?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    fn add_file(&mut self, path: &Path, file: &FileEntry) -> Result<()> {
+        let name = path.as_os_str().as_encoded_bytes().to_vec();
+        if name.len() > u16::MAX as usize {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{path:?} is too long for a zip archive"),
+            ));
+        }
+        let mut entry = CentralEntry {
+            flags: name_flags(&name) | FLAG_DATA_DESCRIPTOR,
+            name,
+            method: self.method.id(),
+            crc: 0,
+            compressed_size: 0,
+            size: file.size() as u64,
+            offset: self.out.count,
+            external_attr: (S_IFREG | PERM as u32) << 16,
+        };
+        let zip64 = entry.size >= ZIP64_FILE_SIZE;
+        self.write_local_header(&entry, zip64)?;
+
+        let data_start = self.out.count;
+        let mut reader = CrcReader::new(file.get_reader());
+        let read = match self.method {
+            ZipMethod::Stored => std::io::copy(&mut reader, &mut self.out)?,
+            ZipMethod::Deflate => {
+                let mut encoder = DeflateEncoder::new(&mut self.out, Compression::default());
+                let read = std::io::copy(&mut reader, &mut encoder)?;
+                encoder.finish()?;
+                read
+            }
+        };
+        check_size(file, read)?;
+        entry.crc = reader.crc().sum();
+        entry.compressed_size = self.out.count - data_start;
+        if !zip64 && entry.compressed_size >= u32::MAX as u64 {
+            return Err(Error::other(format!(
+                "{path:?} is bigger than expected once compressed"
+            )));
+        }
+
+        let mut descriptor = Vec::with_capacity(24);
+        descriptor.extend_from_slice(&DATA_DESCRIPTOR_SIG.to_le_bytes());
+        descriptor.extend_from_slice(&entry.crc.to_le_bytes());
+        if zip64 {
+            descriptor.extend_from_slice(&entry.compressed_size.to_le_bytes());
+            descriptor.extend_from_slice(&entry.size.to_le_bytes());
+        } else {
+            descriptor.extend_from_slice(&(entry.compressed_size as u32).to_le_bytes());
+            descriptor.extend_from_slice(&(entry.size as u32).to_le_bytes());
+        }
+        self.out.write_all(&descriptor)?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.write_central_directory()?;
+        self.out.flush()
+    }
+}
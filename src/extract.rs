@@ -0,0 +1,324 @@
+use super::diff::{list_all, Difference};
+use super::progress::{Progress, ProgressInfo, Tracker};
+use super::tree::{DirEntry, EntryRef, FileEntry};
+
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// What to do with entries already existing on disk.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Overwrite {
+    /// Fail on the first existing entry.
+    #[default]
+    Fail,
+    /// Keep existing entries as they are, without checking them.
+    Skip,
+    /// Rewrite existing files.
+    Replace,
+    /// Check existing files, keeping the correct ones and repairing the
+    /// others. This resumes an interrupted generation.
+    Verify,
+}
+
+impl std::str::FromStr for Overwrite {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "fail" => Ok(Self::Fail),
+            "skip" => Ok(Self::Skip),
+            "replace" => Ok(Self::Replace),
+            "verify" => Ok(Self::Verify),
+            _ => Err(format!(
+                "'{s}' is not an overwrite policy (fail, skip, replace or verify)"
+            )),
+        }
+    }
+}
+
+/// Options of `DirEntry::generate_with`.
+#[derive(Debug, Default, Clone)]
+pub struct GenerateOptions {
+    pub overwrite: Overwrite,
+    /// Write the tree in this sub-directory of the target directory.
+    /// Its missing parent directories are created.
+    pub subdir: Option<PathBuf>,
+    /// Allow the directory the tree is written in to exist already, even if
+    /// not empty. Existing directories are merged with the tree ones and,
+    /// with `Overwrite::Fail`, nothing is written if any other entry conflicts.
+    pub into_existing: bool,
+}
+
+fn already_exists(path: &Path) -> Error {
+    Error::new(ErrorKind::AlreadyExists, format!("{path:?} already exists"))
+}
+
+fn conflict_error(dir: &Path, conflicts: &[PathBuf]) -> Error {
+    const SHOWN: usize = 5;
+    let mut shown = conflicts
+        .iter()
+        .take(SHOWN)
+        .map(|p| format!("{p:?}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if conflicts.len() > SHOWN {
+        shown += &format!(" and {} more", conflicts.len() - SHOWN);
+    }
+    Error::new(
+        ErrorKind::AlreadyExists,
+        format!(
+            "{} entries already exist in {dir:?}: {shown}",
+            conflicts.len()
+        ),
+    )
+}
+
+fn create_dir(path: &Path, overwrite: Overwrite, merge: bool) -> Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Err(e) if e.kind() == ErrorKind::NotFound => std::fs::create_dir(path),
+        Err(e) => Err(e),
+        Ok(m) if m.is_dir() && (merge || overwrite != Overwrite::Fail) => Ok(()),
+        Ok(m) if !m.is_dir() && matches!(overwrite, Overwrite::Replace | Overwrite::Verify) => {
+            std::fs::remove_file(path)?;
+            std::fs::create_dir(path)
+        }
+        Ok(_) => Err(already_exists(path)),
+    }
+}
+
+/// Length of the common prefix of `a` and `b`, reading at most `limit` bytes.
+fn common_prefix(a: &mut dyn Read, b: &mut dyn Read, limit: u64) -> Result<u64> {
+    let (mut a, mut b) = (a.take(limit), b.take(limit));
+    let mut buf_a = vec![0; 64 * 1024];
+    let mut buf_b = vec![0; 64 * 1024];
+    let mut common = 0;
+    loop {
+        let read = a.read(&mut buf_a)?;
+        if read == 0 {
+            return Ok(common);
+        }
+        b.read_exact(&mut buf_b[..read])?;
+        match buf_a[..read]
+            .iter()
+            .zip(&buf_b[..read])
+            .position(|(a, b)| a != b)
+        {
+            Some(idx) => return Ok(common + idx as u64),
+            None => common += read as u64,
+        }
+    }
+}
+
+/// Write the content of `file` to `out` from `offset`.
+fn write_from(file: &FileEntry, out: File, offset: u64, tracker: &mut Tracker) -> Result<()> {
+    let mut reader = file.get_reader();
+    std::io::copy(&mut reader.by_ref().take(offset), &mut std::io::sink())?;
+    std::io::copy(&mut reader, &mut tracker.writer(out))?;
+    Ok(())
+}
+
+fn generate_file(
+    file: &FileEntry,
+    path: &Path,
+    overwrite: Overwrite,
+    tracker: &mut Tracker,
+) -> Result<()> {
+    let existing = match std::fs::symlink_metadata(path) {
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+        Ok(m) => Some(m),
+    };
+    match (existing, overwrite) {
+        (None, _) => {
+            let out = OpenOptions::new().write(true).create_new(true).open(path)?;
+            write_from(file, out, 0, tracker)
+        }
+        (Some(m), Overwrite::Skip) if m.is_file() => {
+            tracker.skip(file.size() as u64);
+            Ok(())
+        }
+        (Some(m), Overwrite::Replace | Overwrite::Verify) if !m.is_file() => {
+            if m.is_dir() {
+                std::fs::remove_dir_all(path)?;
+            } else {
+                std::fs::remove_file(path)?;
+            }
+            let out = OpenOptions::new().write(true).create_new(true).open(path)?;
+            write_from(file, out, 0, tracker)
+        }
+        (Some(_), Overwrite::Replace) => {
+            let out = OpenOptions::new().write(true).truncate(true).open(path)?;
+            write_from(file, out, 0, tracker)
+        }
+        (Some(m), Overwrite::Verify) => {
+            // Keep the correct start of the file (all of it if it is right)
+            // and regenerate the rest.
+            let mut out = OpenOptions::new().read(true).write(true).open(path)?;
+            let size = file.size() as u64;
+            let mut correct = common_prefix(
+                &mut out,
+                &mut file.get_reader(),
+                std::cmp::min(m.len(), size),
+            )?;
+            if correct < size && !file.prefix_stable() {
+                // The correct start cannot be completed, write all again.
+                correct = 0;
+            }
+            if m.len() > correct {
+                out.set_len(correct)?;
+            }
+            tracker.skip(correct);
+            if correct < size {
+                out.seek(SeekFrom::Start(correct))?;
+                write_from(file, out, correct, tracker)?;
+            }
+            Ok(())
+        }
+        (Some(_), _) => Err(already_exists(path)),
+    }
+}
+
+impl DirEntry {
+    pub fn generate(&self, dir: &Path) -> Result<()> {
+        self.generate_with_progress(dir, &mut |_: &ProgressInfo| {})
+            .map(|_| ())
+    }
+
+    /// Same as `generate`, reporting the progress to `progress`.
+    /// Returns the final state (what was written and how long it took).
+    pub fn generate_with_progress(
+        &self,
+        dir: &Path,
+        progress: &mut dyn Progress,
+    ) -> Result<ProgressInfo> {
+        self.generate_with(dir, &GenerateOptions::default(), progress)
+    }
+
+    /// Paths (relative to `dir`) of the entries of the tree conflicting with
+    /// what already exists in `dir`: anything but a directory where the tree
+    /// has a directory.
+    pub fn conflicts(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        let mut conflicts = vec![];
+        self.walk(&mut |path, entry| {
+            match std::fs::symlink_metadata(dir.join(path)) {
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+                Ok(m) if m.is_dir() && matches!(entry, EntryRef::Dir(_)) => {}
+                Ok(_) => conflicts.push(path.to_path_buf()),
+            }
+            Ok(())
+        })?;
+        Ok(conflicts)
+    }
+
+    /// Write the tree in `dir`, handling existing entries as set in `options`.
+    pub fn generate_with(
+        &self,
+        dir: &Path,
+        options: &GenerateOptions,
+        progress: &mut dyn Progress,
+    ) -> Result<ProgressInfo> {
+        let mut total_entries = 0;
+        self.walk(&mut |_, _| {
+            total_entries += 1;
+            Ok(())
+        })?;
+        let mut tracker = Tracker::new(total_entries, self.size() as u64, progress);
+        let overwrite = options.overwrite;
+        let merge = options.into_existing;
+        let path = match &options.subdir {
+            Some(subdir) => dir.join(subdir),
+            None => dir.to_path_buf(),
+        }
+        .join(&self.name);
+        if let (Some(_), Some(parent)) = (&options.subdir, path.parent()) {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| Error::new(e.kind(), format!("Cannot create {parent:?}: {e}")))?;
+        }
+        if merge && overwrite == Overwrite::Fail {
+            let conflicts = self.conflicts(&path)?;
+            if !conflicts.is_empty() {
+                return Err(conflict_error(&path, &conflicts));
+            }
+        }
+        create_dir(&path, overwrite, merge)?;
+        self.walk(&mut |entry_path, entry| {
+            let entry_path = path.join(entry_path);
+            match entry {
+                EntryRef::Dir(_) => create_dir(&entry_path, overwrite, merge)?,
+                EntryRef::File(f) => generate_file(f, &entry_path, overwrite, &mut tracker)?,
+            }
+            tracker.entry_done();
+            Ok(())
+        })?;
+        Ok(tracker.finish())
+    }
+}
+
+fn metadata(path: &Path) -> Result<Option<std::fs::Metadata>> {
+    match std::fs::symlink_metadata(path) {
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+        Ok(m) => Ok(Some(m)),
+    }
+}
+
+fn verify_dir(
+    dir: &DirEntry,
+    root: &Path,
+    path: &Path,
+    differences: &mut Vec<Difference>,
+) -> Result<()> {
+    let mut names = HashSet::new();
+    for sub_dir in dir.dirs() {
+        names.insert(sub_dir.name.as_os_str());
+        let sub_path = path.join(&sub_dir.name);
+        match metadata(&root.join(&sub_path))? {
+            None => list_all(
+                &sub_path,
+                EntryRef::Dir(sub_dir),
+                Difference::Removed,
+                differences,
+            ),
+            Some(m) if m.is_dir() => verify_dir(sub_dir, root, &sub_path, differences)?,
+            Some(_) => differences.push(Difference::Changed(sub_path)),
+        }
+    }
+    for file in dir.files() {
+        names.insert(file.name.as_os_str());
+        let file_path = path.join(&file.name);
+        let size = file.size() as u64;
+        match metadata(&root.join(&file_path))? {
+            None => differences.push(Difference::Removed(file_path)),
+            Some(m) if m.is_file() && m.len() == size => {
+                let mut on_disk = File::open(root.join(&file_path))?;
+                if common_prefix(&mut on_disk, &mut file.get_reader(), size)? != size {
+                    differences.push(Difference::Changed(file_path));
+                }
+            }
+            Some(_) => differences.push(Difference::Changed(file_path)),
+        }
+    }
+    for entry in std::fs::read_dir(root.join(path))? {
+        let name = entry?.file_name();
+        if !names.contains(name.as_os_str()) {
+            differences.push(Difference::Added(path.join(name)));
+        }
+    }
+    Ok(())
+}
+
+impl DirEntry {
+    /// Compare the tree with what is on disk in `dir` (where `generate` would
+    /// have written it). Entries missing on disk are removed, unexpected ones
+    /// (not looked into) are added, and files with a wrong content, or a type
+    /// different from the tree one, are changed.
+    pub fn verify(&self, dir: &Path) -> Result<Vec<Difference>> {
+        let mut differences = vec![];
+        verify_dir(self, &dir.join(&self.name), Path::new(""), &mut differences)?;
+        differences.sort_by(|a, b| a.path().cmp(b.path()));
+        Ok(differences)
+    }
+}
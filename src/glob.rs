@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path};
+
+/// A glob pattern matched against relative paths, component by component.
+///
+/// `*` matches any sequence of characters inside a component, `?` matches one
+/// byte and a `**` component matches zero or more components.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub struct Pattern {
+    source: String,
+    components: Vec<String>,
+}
+
+impl Pattern {
+    pub fn new(source: &str) -> Self {
+        let components = source
+            .split('/')
+            .filter(|c| !c.is_empty() && *c != ".")
+            .map(String::from)
+            .collect();
+        Self {
+            source: source.into(),
+            components,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        let names = path
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(n) => Some(n.to_string_lossy()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let names = names.iter().map(|n| n.as_ref()).collect::<Vec<_>>();
+        match_components(&self.components, &names)
+    }
+}
+
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl From<String> for Pattern {
+    fn from(s: String) -> Self {
+        Self::new(&s)
+    }
+}
+
+impl From<Pattern> for String {
+    fn from(p: Pattern) -> Self {
+        p.source
+    }
+}
+
+fn match_components(pattern: &[String], names: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => names.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            (0..=names.len()).any(|skip| match_components(rest, &names[skip..]))
+        }
+        Some((first, rest)) => match names.split_first() {
+            Some((name, names)) => {
+                match_name(first.as_bytes(), name.as_bytes()) && match_components(rest, names)
+            }
+            None => false,
+        },
+    }
+}
+
+fn match_name(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| match_name(rest, &name[skip..])),
+        Some((b'?', rest)) => !name.is_empty() && match_name(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && match_name(rest, &name[1..]),
+    }
+}
@@ -0,0 +1,79 @@
+use super::tree::{DirEntry, EntryRef, FileEntry};
+
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+impl FileEntry {
+    /// SHA-256 of the content of the file.
+    pub fn sha256(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut self.get_reader(), &mut hasher).expect("Generated content cannot fail");
+        hasher.finalize().into()
+    }
+}
+
+impl DirEntry {
+    /// Digest of the shape of the tree: kind, path and size of every entry.
+    /// Contents are not read, two trees with different seeds may share it.
+    pub fn structure_fingerprint(&self) -> [u8; 32] {
+        self.fingerprint_with(b"structure", None)
+    }
+
+    /// Digest of the structure and the content of all the files.
+    /// Contents are streamed from the generators, on `jobs` threads.
+    pub fn fingerprint(&self, jobs: usize) -> [u8; 32] {
+        let files = self
+            .iter_dfs()
+            .filter_map(|(_, e)| match e {
+                EntryRef::File(f) => Some(f),
+                EntryRef::Dir(_) => None,
+            })
+            .collect::<Vec<_>>();
+        let next = AtomicUsize::new(0);
+        let mut hashes = vec![[0; 32]; files.len()];
+        std::thread::scope(|scope| {
+            let workers = (0..jobs.clamp(1, files.len().max(1)))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut done = vec![];
+                        loop {
+                            let idx = next.fetch_add(1, Ordering::Relaxed);
+                            match files.get(idx) {
+                                Some(file) => done.push((idx, file.sha256())),
+                                None => break done,
+                            }
+                        }
+                    })
+                })
+                .collect::<Vec<_>>();
+            for worker in workers {
+                for (idx, hash) in worker.join().unwrap() {
+                    hashes[idx] = hash;
+                }
+            }
+        });
+        self.fingerprint_with(b"content", Some(&hashes))
+    }
+
+    // `hashes` are the contents hashes of the files, in walk order.
+    fn fingerprint_with(&self, domain: &[u8], hashes: Option<&[[u8; 32]]>) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(domain);
+        let mut hashes = hashes.map(|h| h.iter());
+        for (path, entry) in self.iter_dfs() {
+            let path = path.as_os_str().as_encoded_bytes();
+            let (kind, size) = match entry {
+                EntryRef::Dir(_) => (b'd', 0),
+                EntryRef::File(f) => (if f.is_binary { b'b' } else { b't' }, f.size),
+            };
+            hasher.update([kind]);
+            hasher.update((path.len() as u64).to_le_bytes());
+            hasher.update(path);
+            hasher.update((size as u64).to_le_bytes());
+            if let (EntryRef::File(_), Some(hashes)) = (entry, &mut hashes) {
+                hasher.update(hashes.next().unwrap());
+            }
+        }
+        hasher.finalize().into()
+    }
+}
@@ -1,12 +1,51 @@
+mod config;
+mod diff;
+mod export;
+mod extract;
+mod glob;
+mod hash;
+mod manifest;
+mod mirror;
 #[cfg(feature = "fuse")]
 mod mount;
+mod mutation;
+mod print;
+mod profile;
+mod progress;
 mod random;
+mod range;
+mod rule;
+mod select;
+#[cfg(feature = "serde")]
+mod serialize;
+mod stats;
 mod tree;
+mod walk;
 
+pub use config::{Config, ConfigError, PRESETS};
+pub use diff::Difference;
+#[cfg(feature = "arx")]
+pub use export::ArxWriter;
+pub use export::{CpioWriter, Exporter, TarWriter, ZipMethod, ZipWriter};
+pub use extract::{GenerateOptions, Overwrite};
+pub use glob::Pattern;
+pub use manifest::ManifestError;
 #[cfg(feature = "fuse")]
 pub use mount::TreeFs;
-pub use random::ContextBuilder;
-pub use tree::DirEntry;
+pub use mutation::{Change, Mutations};
+pub use print::{PrintFormat, PrintOptions};
+pub use profile::{Histogram, Profile, ProfileError};
+pub use progress::{Progress, ProgressInfo};
+pub use random::{BuildError, ContextBuilder, GENERATOR_VERSION, GENERATOR_VERSIONS};
+pub use range::{
+    format_range, format_size, format_size_range, parse_range, parse_size, parse_size_range,
+};
+pub use rule::Rule;
+#[cfg(feature = "serde")]
+pub use serialize::SerializeError;
+pub use stats::Stats;
+pub use tree::{DirEntry, EntryRef, FileEntry};
+pub use walk::{BreadthFirst, DepthFirst, Visitor};
 
 #[cfg(feature = "fuse")]
 pub use fuser::BackgroundSession;
@@ -1,101 +1,605 @@
-mod mount;
-mod random;
-mod tree;
-
-use random::ContextBuilder;
-use std::io::Result;
+use arx_test_dir::{
+    parse_range, parse_size_range, Config, ContextBuilder, CpioWriter, DirEntry, EntryRef,
+    FileEntry, GenerateOptions, Mutations, Overwrite, Pattern, PrintFormat, PrintOptions, Profile,
+    Progress, ProgressInfo, TarWriter, ZipMethod, ZipWriter, GENERATOR_VERSION,
+};
+use std::io::{BufReader, BufWriter, IsTerminal, Write};
 use std::ops::Range;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
 
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
 
-fn parse_range<T>(s: &str) -> std::result::Result<Range<T>, String>
-where
-    T: std::str::FromStr,
-    <T as std::str::FromStr>::Err: std::fmt::Debug,
-{
-    let (start, end) = s.split_once("..").ok_or(format!("'{s}' is not a range"))?;
-    let start = start
-        .parse::<T>()
-        .map_err(|e| format!("'{start}' is not a valid value ({e:?})"))?;
-    let end = end
-        .parse::<T>()
-        .map_err(|e| format!("'{end}' is not a valid value ({e:?})"))?;
-    Ok(start..end)
+fn parse_ratio(s: &str) -> std::result::Result<f32, String> {
+    let ratio = s
+        .parse::<f32>()
+        .map_err(|e| format!("'{s}' is not a valid ratio ({e})"))?;
+    if (0.0..=1.0).contains(&ratio) {
+        Ok(ratio)
+    } else {
+        Err(format!("{ratio} is not between 0 and 1"))
+    }
 }
 
-fn parse_range_64(s: &str) -> std::result::Result<Range<u64>, String> {
-    parse_range(s)
-}
+/// Where the tree comes from and how it is changed before being output.
+#[derive(Args)]
+struct TreeArgs {
+    /// Rebuild the tree from a manifest instead of generating it.
+    #[arg(long)]
+    manifest: Option<PathBuf>,
 
-fn parse_range_usize(s: &str) -> std::result::Result<Range<usize>, String> {
-    parse_range(s)
-}
+    /// Reproduce the exact structure of this directory, with generated content.
+    #[arg(long, conflicts_with_all = ["manifest", "scan", "profile"])]
+    mirror: Option<PathBuf>,
 
-#[derive(Parser)]
-struct Cli {
+    /// Start from this named preset.
     #[arg(long)]
-    extract_dir: Option<PathBuf>,
+    preset: Option<String>,
 
-    #[cfg(not(windows))]
+    /// Load generation options from this TOML (or JSON) file.
     #[arg(long)]
-    mount_dir: Option<PathBuf>,
+    config: Option<PathBuf>,
+
+    /// Scan this directory and generate a tree with the same shape.
+    #[arg(long, conflicts_with = "profile")]
+    scan: Option<PathBuf>,
+
+    /// Generate a tree with the shape described in this profile.
+    #[arg(long)]
+    profile: Option<PathBuf>,
+
+    /// Write the profile used to generate the tree to this file.
+    #[arg(long)]
+    write_profile: Option<PathBuf>,
 
     #[arg(long, short)]
     seed: Option<u64>,
 
-    #[arg(long, value_parser = parse_range_64)]
+    /// Version of the generation algorithm, to reproduce trees generated by
+    /// older releases. Manifests record the version of their files.
+    #[arg(long, conflicts_with = "manifest")]
+    generator_version: Option<u32>,
+
+    /// Mutate the tree with this seed: all outputs get the mutated tree.
+    #[arg(long)]
+    mutate: Option<u64>,
+
+    /// Number of changes of each kind made by `--mutate`.
+    #[arg(long, default_value_t = 5, requires = "mutate")]
+    mutations: usize,
+
+    /// Turn this directory, generated from the tree before mutation, into the
+    /// mutated tree.
+    #[arg(long, requires = "mutate")]
+    apply_dir: Option<PathBuf>,
+
+    /// Write the changes made by `--mutate` to this file.
+    #[arg(long, requires = "mutate")]
+    write_changes: Option<PathBuf>,
+
+    /// Only output the entries matching this path or glob (with their content).
+    /// Can be repeated.
+    #[arg(long)]
+    select: Vec<Pattern>,
+
+    #[arg(long, value_parser = parse_range)]
     dir_depth: Option<Range<u64>>,
 
-    #[arg(long, value_parser = parse_range_64)]
+    #[arg(long, value_parser = parse_range)]
     nb_dir_child: Option<Range<u64>>,
 
-    #[arg(long, value_parser = parse_range_64)]
+    #[arg(long, value_parser = parse_range)]
     nb_file_child: Option<Range<u64>>,
 
-    #[arg(long)]
+    /// Probability for a child entry to be a directory.
+    #[arg(long, alias = "dir-ratio", value_parser = parse_ratio)]
     ratio_dir: Option<f32>,
 
-    #[arg(long)]
+    #[arg(long, value_parser = parse_ratio)]
     binary_ratio: Option<f32>,
 
-    #[arg(long, value_parser = parse_range_usize)]
+    #[arg(long, value_parser = parse_size_range)]
     file_len: Option<Range<usize>>,
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+/// What to do with the tree in `generate`.
+#[derive(Args)]
+struct OutputArgs {
+    /// Write the tree in this directory.
+    #[arg(long)]
+    extract_dir: Option<PathBuf>,
+
+    /// What to do with entries already in the extract dir: fail, skip,
+    /// replace or verify (keep correct files and repair the others).
+    #[arg(long, default_value = "fail")]
+    overwrite: Overwrite,
+
+    /// Generate the tree in this sub-directory of the extract dir.
+    #[arg(long, requires = "extract_dir")]
+    subdir: Option<PathBuf>,
+
+    /// Generate the tree in the extract dir even if it exists and is not
+    /// empty (existing directories are merged).
+    #[arg(long, requires = "extract_dir")]
+    into_existing: bool,
+
+    /// Write the tree as a tar archive to this file ("-" for stdout).
+    #[arg(long)]
+    tar: Option<PathBuf>,
+
+    /// Write the tree as a zip archive to this file ("-" for stdout).
+    #[arg(long)]
+    zip: Option<PathBuf>,
+
+    /// Compression method of the zip archive (stored or deflate).
+    #[arg(long, default_value = "deflate")]
+    zip_method: ZipMethod,
 
+    /// Write the tree as a newc cpio archive to this file ("-" for stdout).
+    #[arg(long)]
+    cpio: Option<PathBuf>,
+
+    /// Write the tree as an arx archive to this file.
+    #[cfg(feature = "arx")]
+    #[arg(long)]
+    arx: Option<PathBuf>,
+
+    /// Write the manifest of the tree to this file.
+    #[arg(long)]
+    write_manifest: Option<PathBuf>,
+
+    /// Write the whole tree model to this file, as CBOR if its extension is
+    /// `.cbor`, as JSON otherwise.
+    #[cfg(feature = "serde")]
+    #[arg(long)]
+    write_tree: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Write the tree on disk, as archives or as a manifest.
+    Generate {
+        #[command(flatten)]
+        tree: Box<TreeArgs>,
+
+        #[command(flatten)]
+        output: Box<OutputArgs>,
+    },
+    /// Mount the tree as a read only filesystem.
+    #[cfg(feature = "fuse")]
+    Mount {
+        #[command(flatten)]
+        tree: Box<TreeArgs>,
+
+        mount_dir: PathBuf,
+    },
+    /// Check a directory contains exactly the tree.
+    Verify {
+        #[command(flatten)]
+        tree: Box<TreeArgs>,
+
+        dir: PathBuf,
+    },
+    /// Print statistics of the tree, without generating it.
+    Stats {
+        #[command(flatten)]
+        tree: Box<TreeArgs>,
+
+        /// Print the statistics as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the tree (or one of its directories) with the size of files.
+    Ls {
+        #[command(flatten)]
+        tree: Box<TreeArgs>,
+
+        /// Output format: tree, find (paths only) or long (like `ls -lR`).
+        #[arg(long, default_value = "tree")]
+        format: PrintFormat,
+
+        /// Only print entries down to this depth.
+        #[arg(long)]
+        max_depth: Option<usize>,
+
+        path: Option<PathBuf>,
+    },
+    /// Write the content of a file of the tree to stdout.
+    Cat {
+        #[command(flatten)]
+        tree: Box<TreeArgs>,
+
+        path: PathBuf,
+    },
+    /// Print the SHA-256 of the content of a file of the tree.
+    Hash {
+        #[command(flatten)]
+        tree: Box<TreeArgs>,
+
+        path: PathBuf,
+    },
+    /// Print a digest of the whole tree, to check a fixture has not changed.
+    Fingerprint {
+        #[command(flatten)]
+        tree: Box<TreeArgs>,
+
+        /// Only hash the structure (kinds, paths and sizes), not the contents.
+        #[arg(long)]
+        structure: bool,
+
+        /// Number of threads hashing the contents.
+        #[arg(long, short, default_value = "1")]
+        jobs: usize,
+    },
+    /// List the entries that differ between two trees, given as manifests.
+    Diff { old: PathBuf, new: PathBuf },
+}
+
+#[derive(Parser)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+fn mega_bytes(bytes: f64) -> String {
+    format!("{:.1} MB", bytes / 1_000_000.0)
+}
+
+/// A one line progress bar on stderr, redrawn at most ten times per second.
+struct ProgressBar {
+    last_draw: Option<Instant>,
+}
+
+impl ProgressBar {
+    const WIDTH: usize = 30;
+
+    fn draw(&self, info: &ProgressInfo) {
+        let done = if info.total_bytes > 0 {
+            info.bytes as f64 / info.total_bytes as f64
+        } else {
+            info.entries as f64 / info.total_entries.max(1) as f64
+        };
+        let filled = (done * Self::WIDTH as f64) as usize;
+        let eta = info
+            .eta()
+            .map_or("-".into(), |eta| format!("{}s", eta.as_secs()));
+        eprint!(
+            "\r[{}{}] {:>3.0}% {} entries {}/{} {}/s ETA {eta}   ",
+            "#".repeat(filled),
+            " ".repeat(Self::WIDTH - filled),
+            done * 100.0,
+            info.entries,
+            mega_bytes(info.bytes as f64),
+            mega_bytes(info.total_bytes as f64),
+            mega_bytes(info.rate()),
+        );
+    }
+}
+
+impl Progress for ProgressBar {
+    fn update(&mut self, info: &ProgressInfo) {
+        let now = Instant::now();
+        if self
+            .last_draw
+            .is_none_or(|last| now - last >= Duration::from_millis(100))
+        {
+            self.last_draw = Some(now);
+            self.draw(info);
+        }
+    }
+
+    fn finish(&mut self, info: &ProgressInfo) {
+        self.draw(info);
+        eprintln!();
+    }
+}
+
+/// Open `path` for writing, "-" being stdout.
+fn open_output(path: &Path) -> std::io::Result<BufWriter<Box<dyn Write>>> {
+    let output: Box<dyn Write> = if path == Path::new("-") {
+        Box::new(std::io::stdout().lock())
+    } else {
+        Box::new(std::fs::File::create(path)?)
+    };
+    Ok(BufWriter::new(output))
+}
+
+fn load_manifest(path: &Path) -> Result<DirEntry, Box<dyn std::error::Error>> {
+    let mut input = BufReader::new(std::fs::File::open(path)?);
+    Ok(DirEntry::from_manifest(&mut input)?)
+}
+
+fn diff(old: &Path, new: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let differences = load_manifest(old)?.diff(&load_manifest(new)?);
+    let mut output = BufWriter::new(std::io::stdout().lock());
+    for difference in &differences {
+        writeln!(output, "{difference}")?;
+    }
+    output.flush()?;
+    eprintln!("{} differences", differences.len());
+    Ok(())
+}
+
+fn build_tree(args: TreeArgs) -> Result<DirEntry, Box<dyn std::error::Error>> {
     let mut builder = ContextBuilder::new();
 
-    cli.seed.map(|v| builder.seed(v));
-    cli.dir_depth.map(|v| builder.dir_depth(v));
-    cli.nb_dir_child.map(|v| builder.nb_dir_child(v));
-    cli.nb_file_child.map(|v| builder.nb_file_child(v));
-    cli.binary_ratio.map(|v| builder.binary_ratio(v));
-    cli.file_len.map(|v| builder.file_len(v));
+    if let Some(preset) = &args.preset {
+        Config::preset(preset)?.apply(&mut builder)?;
+    }
+    if let Some(path) = &args.config {
+        Config::load(path)?.apply(&mut builder)?;
+    }
 
-    let context = builder.create();
+    let profile = if let Some(path) = args.scan {
+        eprintln!("Scanning {path:?}");
+        Some(Profile::scan(&path)?)
+    } else if let Some(path) = args.profile {
+        let mut input = BufReader::new(std::fs::File::open(path)?);
+        Some(Profile::read(&mut input)?)
+    } else {
+        None
+    };
+    if let Some(profile) = profile {
+        if let Some(path) = args.write_profile {
+            let mut output = std::io::BufWriter::new(std::fs::File::create(path)?);
+            profile.write(&mut output)?;
+        }
+        builder.profile(profile);
+    }
 
-    println!("Generating with {context:?}");
+    args.generator_version.map(|v| builder.version(v));
+    args.seed.map(|v| builder.seed(v));
+    args.dir_depth.map(|v| builder.dir_depth(v));
+    args.nb_dir_child.map(|v| builder.nb_dir_child(v));
+    args.nb_file_child.map(|v| builder.nb_file_child(v));
+    args.ratio_dir.map(|v| builder.dir_ratio(v));
+    args.binary_ratio.map(|v| builder.binary_ratio(v));
+    args.file_len.map(|v| builder.file_len(v));
 
-    let (_, dir) = tree::DirEntry::new("".into(), 1, 1, context);
+    let dir = if let Some(path) = args.manifest {
+        eprintln!("Loading manifest {path:?}");
+        load_manifest(&path)?
+    } else if let Some(path) = args.mirror {
+        eprintln!("Mirroring {path:?}");
+        DirEntry::mirror(
+            &path,
+            args.seed.unwrap_or(0),
+            args.generator_version.unwrap_or(GENERATOR_VERSION),
+        )?
+    } else {
+        builder.validate()?;
+        let config = toml::to_string(&builder.to_config())?;
+        let context = builder.create()?;
+        eprintln!("Generating with:\n{config}");
+        DirEntry::new_root(context)
+    };
+    let dir = if let Some(seed) = args.mutate {
+        let (mutated, changes) = dir.mutate(seed, &Mutations::uniform(args.mutations));
+        eprintln!("Mutated with {} changes", changes.len());
+        if let Some(path) = args.write_changes {
+            let mut output = std::io::BufWriter::new(std::fs::File::create(path)?);
+            for change in &changes {
+                writeln!(output, "{change}")?;
+            }
+        }
+        if let Some(path) = args.apply_dir {
+            mutated.apply(&changes, &path)?;
+        }
+        mutated
+    } else {
+        dir
+    };
+    if args.select.is_empty() {
+        Ok(dir)
+    } else {
+        Ok(dir.select(&args.select))
+    }
+}
+
+fn stats(tree: TreeArgs, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let stats = build_tree(tree)?.stats();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+    } else {
+        println!("{stats}");
+    }
+    Ok(())
+}
+
+fn generate(tree: TreeArgs, output: OutputArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = build_tree(tree)?;
     let nb_files = dir.nb_files();
     let size = dir.size();
-    println!("Generate {nb_files} files for a {size} bytes.");
+    eprintln!("Generate {nb_files} files for a {size} bytes.");
+
+    if let Some(path) = output.write_manifest {
+        let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+        dir.write_manifest(&mut out)?;
+    }
+
+    #[cfg(feature = "serde")]
+    if let Some(path) = output.write_tree {
+        let mut out = open_output(&path)?;
+        if path.extension().is_some_and(|e| e == "cbor") {
+            dir.write_cbor(&mut out)?;
+        } else {
+            dir.write_json(&mut out)?;
+        }
+        out.flush()?;
+    }
+
+    if let Some(path) = output.extract_dir {
+        let options = GenerateOptions {
+            overwrite: output.overwrite,
+            subdir: output.subdir,
+            into_existing: output.into_existing,
+        };
+        let info = if std::io::stderr().is_terminal() {
+            dir.generate_with(&path, &options, &mut ProgressBar { last_draw: None })?
+        } else {
+            dir.generate_with(&path, &options, &mut |_: &ProgressInfo| {})?
+        };
+        eprintln!(
+            "Wrote {} entries ({} bytes) in {:.2}s, {}/s",
+            info.entries,
+            info.bytes,
+            info.elapsed.as_secs_f64(),
+            mega_bytes(info.rate())
+        );
+    }
+
+    if let Some(path) = output.tar {
+        dir.export(&mut TarWriter::new(open_output(&path)?))?;
+    }
+
+    if let Some(path) = output.zip {
+        dir.export(&mut ZipWriter::new(open_output(&path)?, output.zip_method))?;
+    }
 
-    if let Some(path) = cli.extract_dir {
-        dir.generate(&path)?;
+    if let Some(path) = output.cpio {
+        dir.export(&mut CpioWriter::new(open_output(&path)?))?;
     }
 
-    #[cfg(not(windows))]
-    if let Some(path) = cli.mount_dir {
-        let options = vec![
-            fuser::MountOption::RO,
-            fuser::MountOption::FSName("test_arx".into()),
-        ];
-        fuser::mount2(mount::TreeFs::new(dir), path, &options)?;
+    #[cfg(feature = "arx")]
+    if let Some(path) = output.arx {
+        dir.export(&mut arx_test_dir::ArxWriter::new(&path)?)?;
     }
 
     Ok(())
 }
+
+#[cfg(feature = "fuse")]
+fn mount(tree: TreeArgs, mount_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = build_tree(tree)?;
+    let options = vec![
+        fuser::MountOption::RO,
+        fuser::MountOption::FSName("test_arx".into()),
+    ];
+    fuser::mount2(arx_test_dir::TreeFs::new(dir), mount_dir, &options)?;
+    Ok(())
+}
+
+fn verify(tree: TreeArgs, dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let differences = build_tree(tree)?.verify(dir)?;
+    let mut output = BufWriter::new(std::io::stdout().lock());
+    for difference in &differences {
+        writeln!(output, "{difference}")?;
+    }
+    output.flush()?;
+    if differences.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{dir:?} differs from the tree ({} differences)",
+            differences.len()
+        )
+        .into())
+    }
+}
+
+fn lookup<'a>(dir: &'a DirEntry, path: &Path) -> Result<EntryRef<'a>, String> {
+    dir.get_path(path)
+        .map_err(|_| format!("{path:?} is not in the tree"))
+}
+
+fn ls(
+    tree: TreeArgs,
+    options: PrintOptions,
+    path: Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = build_tree(tree)?;
+    let path = path.unwrap_or_default();
+    let mut output = BufWriter::new(std::io::stdout().lock());
+    match lookup(&dir, &path)? {
+        EntryRef::Dir(d) => {
+            if path.as_os_str().is_empty() {
+                d.print(&mut output, &options)?;
+            } else {
+                // Show the whole path of the directory, not only its name.
+                let mut d = d.clone();
+                d.name = path;
+                d.print(&mut output, &options)?;
+            }
+        }
+        EntryRef::File(f) => f.print(&mut output, &path, options.format)?,
+    }
+    output.flush()?;
+    Ok(())
+}
+
+fn file<'a>(dir: &'a DirEntry, path: &Path) -> Result<&'a FileEntry, String> {
+    match lookup(dir, path)? {
+        EntryRef::File(f) => Ok(f),
+        EntryRef::Dir(_) => Err(format!("{path:?} is a directory")),
+    }
+}
+
+fn cat(tree: TreeArgs, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = build_tree(tree)?;
+    let mut output = BufWriter::new(std::io::stdout().lock());
+    std::io::copy(&mut file(&dir, path)?.get_reader(), &mut output)?;
+    output.flush()?;
+    Ok(())
+}
+
+fn hash(tree: TreeArgs, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = build_tree(tree)?;
+    let hash = file(&dir, path)?.sha256();
+    let hex = hash.iter().map(|b| format!("{b:02x}")).collect::<String>();
+    println!("{hex}  {}", path.display());
+    Ok(())
+}
+
+fn fingerprint(
+    tree: TreeArgs,
+    structure: bool,
+    jobs: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = build_tree(tree)?;
+    let hash = if structure {
+        dir.structure_fingerprint()
+    } else {
+        dir.fingerprint(jobs)
+    };
+    println!(
+        "{}",
+        hash.iter().map(|b| format!("{b:02x}")).collect::<String>()
+    );
+    Ok(())
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
+    match cli.command {
+        Command::Generate { tree, output } => generate(*tree, *output),
+        #[cfg(feature = "fuse")]
+        Command::Mount { tree, mount_dir } => mount(*tree, &mount_dir),
+        Command::Verify { tree, dir } => verify(*tree, &dir),
+        Command::Stats { tree, json } => stats(*tree, json),
+        Command::Ls {
+            tree,
+            format,
+            max_depth,
+            path,
+        } => ls(*tree, PrintOptions { format, max_depth }, path),
+        Command::Cat { tree, path } => cat(*tree, &path),
+        Command::Hash { tree, path } => hash(*tree, &path),
+        Command::Fingerprint {
+            tree,
+            structure,
+            jobs,
+        } => fingerprint(*tree, structure, jobs),
+        Command::Diff { old, new } => diff(&old, &new),
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
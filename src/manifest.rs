@@ -0,0 +1,172 @@
+use super::random::GENERATOR_VERSIONS;
+use super::tree::{DirEntry, EntryRef, FileEntry};
+
+use std::io::{BufRead, Write};
+use std::path::{Component, Path, PathBuf};
+
+// A manifest is a text file with one entry per line:
+//
+//   <kind>\t<size>\t<seed>\t<path>
+//
+// `kind` is `d` for a directory, `b` for a binary file and `t` for a text file.
+// Directories have `-` as size and seed. Paths are relative to the root, use `/`
+// as separator and are listed in generation order (a directory, then its
+// sub-directories, then its files). Empty lines and lines starting with `#` are
+// ignored.
+//
+// A `v\t<version>` line sets the version of the generation algorithm of the
+// files listed after it. Files listed before any such line use version 1.
+
+const HEADER: &str = "# arx_test_dir manifest 1";
+
+#[derive(Debug)]
+pub enum ManifestError {
+    Io(std::io::Error),
+    Parse { line: usize, msg: String },
+}
+
+impl std::fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Cannot read manifest: {e}"),
+            Self::Parse { line, msg } => write!(f, "Invalid manifest at line {line}: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+impl From<std::io::Error> for ManifestError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+fn name_str(name: &Path) -> std::io::Result<&str> {
+    match name.to_str() {
+        Some(s) if !s.contains(['\t', '\n', '\r']) => Ok(s),
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{name:?} cannot be stored in a manifest"),
+        )),
+    }
+}
+
+impl DirEntry {
+    pub fn write_manifest(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        writeln!(out, "{HEADER}")?;
+        // Declare the version upfront rather than before the first file.
+        let mut version = 1;
+        if let Some((_, EntryRef::File(file))) = self
+            .iter_dfs()
+            .find(|(_, e)| matches!(e, EntryRef::File(_)))
+        {
+            if file.version != version {
+                version = file.version;
+                writeln!(out, "v\t{version}")?;
+            }
+        }
+        self.write_manifest_entries(out, "", &mut version)
+    }
+
+    fn write_manifest_entries(
+        &self,
+        out: &mut dyn Write,
+        prefix: &str,
+        version: &mut u32,
+    ) -> std::io::Result<()> {
+        for dir in &self.dirs {
+            let path = format!("{prefix}{}", name_str(&dir.name)?);
+            writeln!(out, "d\t-\t-\t{path}")?;
+            dir.write_manifest_entries(out, &format!("{path}/"), version)?;
+        }
+        for file in &self.files {
+            if file.version != *version {
+                *version = file.version;
+                writeln!(out, "v\t{version}")?;
+            }
+            let kind = if file.is_binary { 'b' } else { 't' };
+            writeln!(
+                out,
+                "{kind}\t{}\t{}\t{prefix}{}",
+                file.size,
+                file.seed,
+                name_str(&file.name)?
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn from_manifest(input: &mut dyn BufRead) -> Result<Self, ManifestError> {
+        let mut root = DirEntry::empty("".into());
+        let mut version = 1;
+        for (idx, line) in input.lines().enumerate() {
+            let line = line?;
+            let parse_error = |msg: String| ManifestError::Parse { line: idx + 1, msg };
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(value) = line.strip_prefix("v\t") {
+                version = value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|v| GENERATOR_VERSIONS.contains(v))
+                    .ok_or_else(|| parse_error(format!("invalid version '{value}'")))?;
+                continue;
+            }
+            let mut fields = line.splitn(4, '\t');
+            let (kind, size, seed, path) =
+                match (fields.next(), fields.next(), fields.next(), fields.next()) {
+                    (Some(kind), Some(size), Some(seed), Some(path)) => (kind, size, seed, path),
+                    _ => return Err(parse_error("expected 4 tab separated fields".into())),
+                };
+            let (parent, name) = split_path(path).map_err(parse_error)?;
+            let parent = root.get_dir_mut(&parent).ok_or_else(|| {
+                parse_error(format!("parent directory of '{path}' is not declared"))
+            })?;
+            if parent.get_child(&name).is_ok() {
+                return Err(parse_error(format!("'{path}' is declared twice")));
+            }
+            match kind {
+                "d" => parent.dirs.push(DirEntry::empty(name)),
+                "b" | "t" => {
+                    let size = size
+                        .parse::<usize>()
+                        .map_err(|e| parse_error(format!("invalid size '{size}' ({e})")))?;
+                    let seed = seed
+                        .parse::<u64>()
+                        .map_err(|e| parse_error(format!("invalid seed '{seed}' ({e})")))?;
+                    parent
+                        .files
+                        .push(FileEntry::new(name, 0, 0, seed, kind == "b", size, version));
+                }
+                _ => return Err(parse_error(format!("unknown entry kind '{kind}'"))),
+            }
+        }
+        root.renumber(1, 1);
+        Ok(root)
+    }
+
+    fn get_dir_mut(&mut self, path: &[PathBuf]) -> Option<&mut DirEntry> {
+        match path.split_first() {
+            None => Some(self),
+            Some((first, rest)) => self
+                .dirs
+                .iter_mut()
+                .find(|d| &d.name == first)
+                .and_then(|d| d.get_dir_mut(rest)),
+        }
+    }
+}
+
+fn split_path(path: &str) -> Result<(Vec<PathBuf>, PathBuf), String> {
+    let mut components = Vec::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(c) => components.push(PathBuf::from(c)),
+            _ => return Err(format!("'{path}' is not a normalized relative path")),
+        }
+    }
+    let name = components.pop().ok_or_else(|| "empty path".to_string())?;
+    Ok((components, name))
+}
@@ -0,0 +1,64 @@
+use super::profile::is_binary;
+use super::random::{BuildError, GENERATOR_VERSIONS};
+use super::tree::{DirEntry, FileEntry};
+
+use rand::prelude::*;
+use std::path::{Path, PathBuf};
+
+impl DirEntry {
+    /// Build a tree with exactly the same paths and file sizes as the directory
+    /// at `path`. File contents are not read (except to guess if they are binary)
+    /// and are replaced by generated content seeded from `seed`, with the
+    /// generator `version`.
+    ///
+    /// Entries are visited in name order so the result does not depend on the
+    /// order the filesystem lists them. Symlinks and special files are skipped.
+    pub fn mirror(path: &Path, seed: u64, version: u32) -> std::io::Result<Self> {
+        if !GENERATOR_VERSIONS.contains(&version) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                BuildError::UnknownVersion(version),
+            ));
+        }
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let mut root = Self::mirror_dir("".into(), path, version, &mut rng)?;
+        root.renumber(1, 1);
+        Ok(root)
+    }
+
+    fn mirror_dir(
+        name: PathBuf,
+        path: &Path,
+        version: u32,
+        rng: &mut SmallRng,
+    ) -> std::io::Result<Self> {
+        let mut entries = std::fs::read_dir(path)?.collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|e| e.file_name());
+        let mut dir = DirEntry::empty(name);
+        for entry in entries {
+            let file_type = entry.file_type()?;
+            let entry_path = entry.path();
+            if file_type.is_dir() {
+                dir.dirs.push(Self::mirror_dir(
+                    entry.file_name().into(),
+                    &entry_path,
+                    version,
+                    rng,
+                )?);
+            } else if file_type.is_file() {
+                let size = entry.metadata()?.len() as usize;
+                let is_binary = size > 0 && is_binary(&entry_path)?;
+                dir.files.push(FileEntry::new(
+                    entry.file_name().into(),
+                    0,
+                    0,
+                    rng.gen(),
+                    is_binary,
+                    size,
+                    version,
+                ));
+            }
+        }
+        Ok(dir)
+    }
+}
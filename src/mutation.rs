@@ -0,0 +1,452 @@
+use super::random::{name, GENERATOR_VERSION};
+use super::tree::{DirEntry, EntryRef, FileEntry};
+
+use rand::prelude::*;
+use std::fs::OpenOptions;
+use std::io::{Read, Result};
+use std::path::{Path, PathBuf};
+
+/// How many changes of each kind a mutation makes.
+///
+/// Counts are upper bounds: a tree too small to provide enough distinct
+/// entries gets fewer changes. The change list tells what was really done.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Mutations {
+    /// New files.
+    pub added: usize,
+    /// Removed files or directories (with their content).
+    pub deleted: usize,
+    /// Files or directories renamed in the same directory.
+    pub renamed: usize,
+    /// Files or directories moved to another directory, keeping their name.
+    pub moved: usize,
+    /// Files made shorter, keeping the start of their content.
+    pub truncated: usize,
+    /// Files made longer, keeping their current content.
+    pub appended: usize,
+    /// Files with a new content of the same size.
+    pub modified: usize,
+}
+
+impl Mutations {
+    /// `count` changes of each kind.
+    pub fn uniform(count: usize) -> Self {
+        Self {
+            added: count,
+            deleted: count,
+            renamed: count,
+            moved: count,
+            truncated: count,
+            appended: count,
+            modified: count,
+        }
+    }
+}
+
+/// A change made by a mutation. Paths are relative to the root of the tree.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Added(PathBuf),
+    Deleted(PathBuf),
+    Renamed {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    Moved {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    Truncated {
+        path: PathBuf,
+        from: usize,
+        to: usize,
+    },
+    Appended {
+        path: PathBuf,
+        from: usize,
+        to: usize,
+    },
+    Modified(PathBuf),
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Added(path) => write!(f, "added\t{}", path.display()),
+            Self::Deleted(path) => write!(f, "deleted\t{}", path.display()),
+            Self::Renamed { from, to } => {
+                write!(f, "renamed\t{} -> {}", from.display(), to.display())
+            }
+            Self::Moved { from, to } => write!(f, "moved\t{} -> {}", from.display(), to.display()),
+            Self::Truncated { path, from, to } => {
+                write!(f, "truncated\t{} ({from} -> {to})", path.display())
+            }
+            Self::Appended { path, from, to } => {
+                write!(f, "appended\t{} ({from} -> {to})", path.display())
+            }
+            Self::Modified(path) => write!(f, "modified\t{}", path.display()),
+        }
+    }
+}
+
+enum Entry {
+    File(FileEntry),
+    Dir(DirEntry),
+}
+
+impl Entry {
+    fn name_mut(&mut self) -> &mut PathBuf {
+        match self {
+            Self::File(f) => &mut f.name,
+            Self::Dir(d) => &mut d.name,
+        }
+    }
+}
+
+impl DirEntry {
+    fn dir_mut(&mut self, path: &Path) -> Option<&mut DirEntry> {
+        let mut dir = self;
+        for name in path {
+            dir = dir.dirs.iter_mut().find(|d| d.name == name)?;
+        }
+        Some(dir)
+    }
+
+    fn file_mut(&mut self, path: &Path) -> Option<&mut FileEntry> {
+        let name = path.file_name()?;
+        let dir = self.dir_mut(path.parent()?)?;
+        dir.files.iter_mut().find(|f| f.name == name)
+    }
+
+    fn file(&self, path: &Path) -> Option<&FileEntry> {
+        let mut dir = self;
+        for name in path.parent()? {
+            dir = dir.dirs.iter().find(|d| d.name == name)?;
+        }
+        let name = path.file_name()?;
+        dir.files.iter().find(|f| f.name == name)
+    }
+
+    fn has_child(&self, name: &Path) -> bool {
+        self.get_child(name).is_ok()
+    }
+
+    fn take(&mut self, path: &Path) -> Option<Entry> {
+        let dir = self.dir_mut(path.parent()?)?;
+        let name = path.file_name()?;
+        if let Some(idx) = dir.files.iter().position(|f| f.name == name) {
+            Some(Entry::File(dir.files.remove(idx)))
+        } else {
+            let idx = dir.dirs.iter().position(|d| d.name == name)?;
+            Some(Entry::Dir(dir.dirs.remove(idx)))
+        }
+    }
+
+    fn put(&mut self, dir_path: &Path, entry: Entry) {
+        let dir = self.dir_mut(dir_path).expect("Destination must exist");
+        match entry {
+            Entry::File(f) => dir.files.push(f),
+            Entry::Dir(d) => dir.dirs.push(d),
+        }
+    }
+
+    /// Paths of all the entries below `self`, with `true` for directories.
+    fn entry_paths(&self) -> Vec<(PathBuf, bool)> {
+        let mut paths = vec![];
+        self.walk(&mut |path, entry| {
+            paths.push((path.to_path_buf(), matches!(entry, EntryRef::Dir(_))));
+            Ok(())
+        })
+        .expect("Collecting paths cannot fail");
+        paths
+    }
+
+    /// Produce "version 2" of this tree: a copy with the changes described by
+    /// `mutations`, randomly chosen from `seed`.
+    ///
+    /// Each entry is changed at most once (a directory is not changed if
+    /// something inside it is). Changes are listed in the order they are
+    /// made, each path being relative to the tree as it is right before the
+    /// change: content changes first, then renames, moves, deletions and
+    /// finally additions.
+    pub fn mutate(&self, seed: u64, mutations: &Mutations) -> (Self, Vec<Change>) {
+        let mut mutator = Mutator {
+            tree: self.clone(),
+            rng: SmallRng::seed_from_u64(seed),
+            touched: vec![],
+            changes: vec![],
+        };
+        mutator.run(mutations);
+        let Mutator {
+            mut tree, changes, ..
+        } = mutator;
+        tree.renumber(1, 1);
+        (tree, changes)
+    }
+
+    /// Turn the directory `dir`, generated from the tree `mutate` was called
+    /// on, into this mutated tree by applying `changes` on disk.
+    ///
+    /// Only the changed entries are touched: files are renamed, moved and
+    /// deleted rather than regenerated, truncated and appended files keep
+    /// their existing content (except version 1 binary files, written again).
+    pub fn apply(&self, changes: &[Change], dir: &Path) -> Result<()> {
+        // Entries are changed only once, so the content of changed files
+        // can be taken from the final tree.
+        let file = |path: &Path| {
+            self.file(path).ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("{path:?} is not a file of the mutated tree"),
+                )
+            })
+        };
+        let content = |path: &Path| file(path).map(FileEntry::get_reader);
+        for change in changes {
+            match change {
+                Change::Added(path) => {
+                    let mut file = OpenOptions::new()
+                        .write(true)
+                        .create_new(true)
+                        .open(dir.join(path))?;
+                    std::io::copy(&mut content(path)?, &mut file)?;
+                }
+                Change::Deleted(path) => {
+                    let path = dir.join(path);
+                    if path.is_dir() {
+                        std::fs::remove_dir_all(path)?;
+                    } else {
+                        std::fs::remove_file(path)?;
+                    }
+                }
+                Change::Renamed { from, to } | Change::Moved { from, to } => {
+                    std::fs::rename(dir.join(from), dir.join(to))?;
+                }
+                Change::Truncated { path, to, .. } if file(path)?.prefix_stable() => {
+                    let file = OpenOptions::new().write(true).open(dir.join(path))?;
+                    file.set_len(*to as u64)?;
+                }
+                Change::Appended { path, from, .. } if file(path)?.prefix_stable() => {
+                    let mut file = OpenOptions::new().append(true).open(dir.join(path))?;
+                    let mut reader = content(path)?;
+                    std::io::copy(
+                        &mut reader.by_ref().take(*from as u64),
+                        &mut std::io::sink(),
+                    )?;
+                    std::io::copy(&mut reader, &mut file)?;
+                }
+                Change::Truncated { path, .. }
+                | Change::Appended { path, .. }
+                | Change::Modified(path) => {
+                    let mut file = OpenOptions::new()
+                        .write(true)
+                        .truncate(true)
+                        .open(dir.join(path))?;
+                    std::io::copy(&mut content(path)?, &mut file)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Mutator {
+    tree: DirEntry,
+    rng: SmallRng,
+    /// Paths (in the mutated tree) of the entries already changed.
+    touched: Vec<PathBuf>,
+    changes: Vec<Change>,
+}
+
+impl Mutator {
+    fn run(&mut self, mutations: &Mutations) {
+        for _ in 0..mutations.truncated {
+            self.truncate();
+        }
+        for _ in 0..mutations.appended {
+            self.append();
+        }
+        for _ in 0..mutations.modified {
+            self.modify();
+        }
+        for _ in 0..mutations.renamed {
+            self.rename();
+        }
+        for _ in 0..mutations.moved {
+            self.move_entry();
+        }
+        for _ in 0..mutations.deleted {
+            self.delete();
+        }
+        for _ in 0..mutations.added {
+            self.add();
+        }
+    }
+
+    fn is_free(&self, path: &Path) -> bool {
+        self.touched
+            .iter()
+            .all(|t| !t.starts_with(path) && !path.starts_with(t))
+    }
+
+    /// Untouched entries (directories only if `dirs` is true, files only if
+    /// `files` is), in random order.
+    fn candidates(&mut self, files: bool, dirs: bool) -> Vec<PathBuf> {
+        let mut paths = self
+            .tree
+            .entry_paths()
+            .into_iter()
+            .filter(|(p, is_dir)| if *is_dir { dirs } else { files } && self.is_free(p))
+            .map(|(p, _)| p)
+            .collect::<Vec<_>>();
+        paths.shuffle(&mut self.rng);
+        paths
+    }
+
+    /// Pick an untouched file accepted by `filter`.
+    fn pick_file(&mut self, filter: impl Fn(&FileEntry) -> bool) -> Option<PathBuf> {
+        let candidates = self.candidates(true, false);
+        candidates
+            .into_iter()
+            .find(|p| filter(self.tree.file_mut(p).unwrap()))
+    }
+
+    fn new_name(&mut self, dir: &Path, template: &Path) -> PathBuf {
+        let len = template.file_stem().map_or(7, |s| s.len().max(1));
+        let dir = self.tree.dir_mut(dir).expect("Directory must exist");
+        loop {
+            let mut new_name = PathBuf::from(name(&mut self.rng, len));
+            if let Some(extension) = template.extension() {
+                new_name.set_extension(extension);
+            }
+            if !dir.has_child(&new_name) {
+                return new_name;
+            }
+        }
+    }
+
+    fn truncate(&mut self) {
+        if let Some(path) = self.pick_file(|f| f.size > 0) {
+            let file = self.tree.file_mut(&path).unwrap();
+            let from = file.size;
+            let to = self.rng.gen_range(0..from);
+            file.size = to;
+            self.touched.push(path.clone());
+            self.changes.push(Change::Truncated { path, from, to });
+        }
+    }
+
+    fn append(&mut self) {
+        if let Some(path) = self.pick_file(|_| true) {
+            let file = self.tree.file_mut(&path).unwrap();
+            let from = file.size;
+            let to = from + self.rng.gen_range(1..=from.max(1024));
+            file.size = to;
+            self.touched.push(path.clone());
+            self.changes.push(Change::Appended { path, from, to });
+        }
+    }
+
+    fn modify(&mut self) {
+        if let Some(path) = self.pick_file(|f| f.size > 0) {
+            let file = self.tree.file_mut(&path).unwrap();
+            let old_seed = file.seed;
+            while file.seed == old_seed {
+                file.seed = self.rng.gen();
+            }
+            self.touched.push(path.clone());
+            self.changes.push(Change::Modified(path));
+        }
+    }
+
+    fn rename(&mut self) {
+        if let Some(from) = self.candidates(true, true).pop() {
+            let parent = from.parent().unwrap().to_path_buf();
+            let new_name = self.new_name(&parent, &from);
+            let mut entry = self.tree.take(&from).unwrap();
+            *entry.name_mut() = new_name.clone();
+            self.tree.put(&parent, entry);
+            let to = parent.join(new_name);
+            self.touched.push(to.clone());
+            self.changes.push(Change::Renamed { from, to });
+        }
+    }
+
+    fn move_entry(&mut self) {
+        let mut dirs = self
+            .tree
+            .entry_paths()
+            .into_iter()
+            .filter(|(_, is_dir)| *is_dir)
+            .map(|(p, _)| p)
+            .collect::<Vec<_>>();
+        dirs.push(PathBuf::new());
+        for from in self.candidates(true, true) {
+            let parent = from.parent().unwrap();
+            let name = Path::new(from.file_name().unwrap());
+            let destinations = dirs
+                .iter()
+                .filter(|d| d.as_path() != parent && !d.starts_with(&from))
+                .filter(|d| !self.tree.dir_mut(d).unwrap().has_child(name))
+                .collect::<Vec<_>>();
+            if let Some(dest) = destinations.choose(&mut self.rng) {
+                let dest = dest.to_path_buf();
+                let entry = self.tree.take(&from).unwrap();
+                self.tree.put(&dest, entry);
+                let to = dest.join(name);
+                self.touched.push(to.clone());
+                self.changes.push(Change::Moved { from, to });
+                return;
+            }
+        }
+    }
+
+    fn delete(&mut self) {
+        if let Some(path) = self.candidates(true, true).pop() {
+            self.tree.take(&path);
+            self.touched.push(path.clone());
+            self.changes.push(Change::Deleted(path));
+        }
+    }
+
+    fn add(&mut self) {
+        let mut dirs = self.candidates(false, true);
+        dirs.push(PathBuf::new());
+        let dir = dirs.choose(&mut self.rng).unwrap().clone();
+        // New files look like an existing one (kind, extension, size order).
+        let template = self
+            .tree
+            .entry_paths()
+            .into_iter()
+            .filter(|(_, is_dir)| !is_dir)
+            .map(|(p, _)| p)
+            .choose(&mut self.rng);
+        let (template_name, is_binary, max_size, version) = match &template {
+            Some(path) => {
+                let file = self.tree.file_mut(path).unwrap();
+                (file.name.clone(), file.is_binary, file.size, file.version)
+            }
+            None => (
+                PathBuf::from("template.text"),
+                false,
+                1024,
+                GENERATOR_VERSION,
+            ),
+        };
+        let new_name = self.new_name(&dir, &template_name);
+        let size = self.rng.gen_range(0..=max_size);
+        let file = FileEntry::new(
+            new_name.clone(),
+            0,
+            0,
+            self.rng.gen(),
+            is_binary,
+            size,
+            version,
+        );
+        self.tree.put(&dir, Entry::File(file));
+        let path = dir.join(new_name);
+        self.touched.push(path.clone());
+        self.changes.push(Change::Added(path));
+    }
+}
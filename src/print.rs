@@ -0,0 +1,166 @@
+use super::tree::{DirEntry, EntryRef, FileEntry, GID, PERM, UID};
+
+use std::io::{Result, Write};
+use std::path::{Path, PathBuf};
+
+/// How `DirEntry::print` renders a tree.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PrintFormat {
+    /// Indented, like `tree -s`.
+    #[default]
+    Tree,
+    /// One path per line, like `find`.
+    Find,
+    /// One section per directory with the entries metadata, like `ls -lR`.
+    Long,
+}
+
+impl std::str::FromStr for PrintFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "tree" => Ok(Self::Tree),
+            "find" => Ok(Self::Find),
+            "long" => Ok(Self::Long),
+            _ => Err(format!("'{s}' is not a print format (tree, find or long)")),
+        }
+    }
+}
+
+/// Options of `DirEntry::print`.
+#[derive(Debug, Default, Clone)]
+pub struct PrintOptions {
+    pub format: PrintFormat,
+    /// Only print entries down to this depth (children of the root are at
+    /// depth 1).
+    pub max_depth: Option<usize>,
+}
+
+impl DirEntry {
+    /// Render the tree in a human readable format. Entries are printed in
+    /// generation order.
+    pub fn print(&self, out: &mut dyn Write, options: &PrintOptions) -> Result<()> {
+        let root = if self.name.as_os_str().is_empty() {
+            PathBuf::from(".")
+        } else {
+            self.name.clone()
+        };
+        let max_depth = options.max_depth.unwrap_or(usize::MAX);
+        match options.format {
+            PrintFormat::Tree => {
+                writeln!(out, "{}", root.display())?;
+                let (nb_dirs, nb_files) = self.print_tree(out, "", max_depth)?;
+                writeln!(out, "\n{nb_dirs} directories, {nb_files} files")
+            }
+            PrintFormat::Find => {
+                writeln!(out, "{}", root.display())?;
+                for (path, _) in self.iter_dfs() {
+                    if path.components().count() <= max_depth {
+                        writeln!(out, "{}", root.join(path).display())?;
+                    }
+                }
+                Ok(())
+            }
+            PrintFormat::Long => self.print_long(out, &root, max_depth),
+        }
+    }
+
+    fn print_tree(
+        &self,
+        out: &mut dyn Write,
+        prefix: &str,
+        max_depth: usize,
+    ) -> Result<(u64, u64)> {
+        let (mut nb_dirs, mut nb_files) = (0, 0);
+        if max_depth == 0 {
+            return Ok((nb_dirs, nb_files));
+        }
+        let nb_children = self.get_nb_children();
+        let children = self
+            .dirs
+            .iter()
+            .map(EntryRef::Dir)
+            .chain(self.files.iter().map(EntryRef::File));
+        for (idx, child) in children.enumerate() {
+            let last = idx + 1 == nb_children;
+            let (branch, indent) = if last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            match child {
+                EntryRef::Dir(dir) => {
+                    nb_dirs += 1;
+                    writeln!(out, "{prefix}{branch}{}", dir.name.display())?;
+                    let (d, f) =
+                        dir.print_tree(out, &format!("{prefix}{indent}"), max_depth - 1)?;
+                    nb_dirs += d;
+                    nb_files += f;
+                }
+                EntryRef::File(file) => {
+                    nb_files += 1;
+                    writeln!(
+                        out,
+                        "{prefix}{branch}[{:>10}]  {}",
+                        file.size(),
+                        file.name.display()
+                    )?;
+                }
+            }
+        }
+        Ok((nb_dirs, nb_files))
+    }
+
+    fn print_long(&self, out: &mut dyn Write, path: &Path, max_depth: usize) -> Result<()> {
+        writeln!(out, "{}:", path.display())?;
+        if max_depth == 0 {
+            return Ok(());
+        }
+        for dir in &self.dirs {
+            long_line(out, 'd', 2, 0, &dir.name)?;
+        }
+        for file in &self.files {
+            long_line(out, '-', 1, file.size(), &file.name)?;
+        }
+        if max_depth > 1 {
+            for dir in &self.dirs {
+                writeln!(out)?;
+                dir.print_long(out, &path.join(&dir.name), max_depth - 1)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn mode_string(kind: char) -> String {
+    let mut mode = String::from(kind);
+    for shift in [6, 3, 0] {
+        let bits = (PERM >> shift) & 0o7;
+        mode.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        mode.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        mode.push(if bits & 0o1 != 0 { 'x' } else { '-' });
+    }
+    mode
+}
+
+// All entries have the same owner and are dated from the epoch.
+fn long_line(out: &mut dyn Write, kind: char, nlink: u32, size: usize, name: &Path) -> Result<()> {
+    writeln!(
+        out,
+        "{} {nlink} {UID} {GID} {size:>10} Jan  1  1970 {}",
+        mode_string(kind),
+        name.display()
+    )
+}
+
+impl FileEntry {
+    /// Render this file alone, as `DirEntry::print` would in `format`.
+    pub fn print(&self, out: &mut dyn Write, path: &Path, format: PrintFormat) -> Result<()> {
+        match format {
+            PrintFormat::Tree => writeln!(out, "[{:>10}]  {}", self.size(), path.display()),
+            PrintFormat::Find => writeln!(out, "{}", path.display()),
+            PrintFormat::Long => long_line(out, '-', 1, self.size(), path),
+        }
+    }
+}
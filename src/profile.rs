@@ -0,0 +1,307 @@
+use rand::prelude::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::{BufRead, Read, Write};
+use std::path::Path;
+
+// A profile is the shape of an existing directory, without any of its content.
+// It is stored as a text file with one `<key> <value>...` per line. Histograms
+// are stored as `<value>:<count>` pairs.
+
+const HEADER: &str = "# arx_test_dir profile 1";
+
+// Number of bytes read from the head of files to guess if they are binary.
+const SNIFF_LEN: u64 = 1024;
+
+#[derive(Debug)]
+pub enum ProfileError {
+    Io(std::io::Error),
+    Parse { line: usize, msg: String },
+}
+
+impl std::fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Cannot read profile: {e}"),
+            Self::Parse { line, msg } => write!(f, "Invalid profile at line {line}: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {}
+
+impl From<std::io::Error> for ProfileError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct Histogram<K: Ord>(BTreeMap<K, u64>);
+
+impl<K: Ord + std::fmt::Display> std::fmt::Display for Histogram<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let values = self
+            .iter()
+            .map(|(v, c)| format!("{v}:{c}"))
+            .collect::<Vec<_>>();
+        write!(f, "{}", values.join(" "))
+    }
+}
+
+impl<K: Ord> Histogram<K> {
+    pub fn add(&mut self, key: K) {
+        *self.0.entry(key).or_default() += 1;
+    }
+
+    pub fn total(&self) -> u64 {
+        self.0.values().sum()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &u64)> {
+        self.0.iter()
+    }
+
+    pub fn max_key(&self) -> Option<&K> {
+        self.0.keys().next_back()
+    }
+
+    /// Pick a key with a probability proportional to its count.
+    pub fn sample(&self, rng: &mut SmallRng) -> Option<&K> {
+        let total = self.total();
+        if total == 0 {
+            return None;
+        }
+        let mut pick = rng.gen_range(0..total);
+        for (key, count) in &self.0 {
+            if pick < *count {
+                return Some(key);
+            }
+            pick -= count;
+        }
+        unreachable!()
+    }
+}
+
+/// Guess if the file at `path` is binary by looking at its first bytes.
+pub(crate) fn is_binary(path: &Path) -> std::io::Result<bool> {
+    let mut head = Vec::new();
+    std::fs::File::open(path)?
+        .take(SNIFF_LEN)
+        .read_to_end(&mut head)?;
+    // A multi-byte character may be cut at the end of `head`, this is not an error.
+    let invalid_utf8 = std::str::from_utf8(&head).is_err_and(|e| e.error_len().is_some());
+    Ok(head.contains(&0) || invalid_utf8)
+}
+
+pub(crate) fn size_bucket(size: u64) -> u64 {
+    if size == 0 {
+        0
+    } else {
+        1 << size.ilog2()
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Profile {
+    pub nb_dirs: u64,
+    pub nb_files: u64,
+    pub nb_binary_files: u64,
+    pub nb_empty_files: u64,
+    pub nb_empty_dirs: u64,
+    pub nb_symlinks: u64,
+    /// Number of directories per depth (root is at depth 0).
+    pub depth: Histogram<u64>,
+    /// Number of sub-directories per directory.
+    pub dir_fanout: Histogram<u64>,
+    /// Number of files per directory.
+    pub file_fanout: Histogram<u64>,
+    /// File sizes, bucketed by power of two.
+    pub file_size: Histogram<u64>,
+    /// Length of names, extension excluded.
+    pub name_len: Histogram<u64>,
+    /// File extensions. Files without extension are counted in "".
+    pub extension: Histogram<String>,
+}
+
+impl Profile {
+    /// Walk `path` (without following symlinks) and record its shape.
+    pub fn scan(path: &Path) -> std::io::Result<Self> {
+        let mut profile = Self::default();
+        profile.scan_dir(path, 0)?;
+        Ok(profile)
+    }
+
+    fn scan_dir(&mut self, path: &Path, depth: u64) -> std::io::Result<()> {
+        self.nb_dirs += 1;
+        self.depth.add(depth);
+        let (mut nb_dirs, mut nb_files, mut nb_entries) = (0, 0, 0);
+        for entry in std::fs::read_dir(path)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            let entry_path = entry.path();
+            nb_entries += 1;
+            if file_type.is_symlink() {
+                self.nb_symlinks += 1;
+            } else if file_type.is_dir() {
+                nb_dirs += 1;
+                self.add_name(&entry_path, false);
+                self.scan_dir(&entry_path, depth + 1)?;
+            } else if file_type.is_file() {
+                nb_files += 1;
+                self.add_name(&entry_path, true);
+                self.scan_file(&entry_path, entry.metadata()?.len())?;
+            }
+        }
+        if nb_entries == 0 {
+            self.nb_empty_dirs += 1;
+        }
+        self.dir_fanout.add(nb_dirs);
+        self.file_fanout.add(nb_files);
+        Ok(())
+    }
+
+    fn add_name(&mut self, path: &Path, is_file: bool) {
+        let name = if is_file {
+            path.file_stem()
+        } else {
+            path.file_name()
+        };
+        let name_len = name.map_or(0, |n| n.len() as u64);
+        self.name_len.add(name_len);
+        if is_file {
+            let extension = path
+                .extension()
+                .map(|e| e.to_string_lossy().into_owned())
+                .filter(|e| !e.contains(char::is_whitespace))
+                .unwrap_or_default();
+            self.extension.add(extension);
+        }
+    }
+
+    fn scan_file(&mut self, path: &Path, size: u64) -> std::io::Result<()> {
+        self.nb_files += 1;
+        self.file_size.add(size_bucket(size));
+        if size == 0 {
+            self.nb_empty_files += 1;
+            return Ok(());
+        }
+        if is_binary(path)? {
+            self.nb_binary_files += 1;
+        }
+        Ok(())
+    }
+
+    pub fn binary_ratio(&self) -> f32 {
+        if self.nb_files == 0 {
+            0.0
+        } else {
+            self.nb_binary_files as f32 / self.nb_files as f32
+        }
+    }
+
+    pub fn max_depth(&self) -> u64 {
+        self.depth.max_key().copied().unwrap_or(0)
+    }
+
+    pub(crate) fn sample_file_len(&self, rng: &mut SmallRng) -> usize {
+        match self.file_size.sample(rng) {
+            None | Some(0) => 0,
+            Some(&bucket) => rng.gen_range(bucket..bucket * 2) as usize,
+        }
+    }
+
+    pub(crate) fn sample_name_len(&self, rng: &mut SmallRng) -> usize {
+        self.name_len
+            .sample(rng)
+            .map_or(7, |l| (*l).max(1) as usize)
+    }
+
+    pub(crate) fn sample_extension(&self, rng: &mut SmallRng) -> Option<String> {
+        self.extension.sample(rng).cloned()
+    }
+
+    pub fn write(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        writeln!(out, "{HEADER}")?;
+        writeln!(out, "nb_dirs {}", self.nb_dirs)?;
+        writeln!(out, "nb_files {}", self.nb_files)?;
+        writeln!(out, "nb_binary_files {}", self.nb_binary_files)?;
+        writeln!(out, "nb_empty_files {}", self.nb_empty_files)?;
+        writeln!(out, "nb_empty_dirs {}", self.nb_empty_dirs)?;
+        writeln!(out, "nb_symlinks {}", self.nb_symlinks)?;
+        write_histogram(out, "depth", &self.depth)?;
+        write_histogram(out, "dir_fanout", &self.dir_fanout)?;
+        write_histogram(out, "file_fanout", &self.file_fanout)?;
+        write_histogram(out, "file_size", &self.file_size)?;
+        write_histogram(out, "name_len", &self.name_len)?;
+        write_histogram(out, "extension", &self.extension)
+    }
+
+    pub fn read(input: &mut dyn BufRead) -> Result<Self, ProfileError> {
+        let mut profile = Self::default();
+        for (idx, line) in input.lines().enumerate() {
+            let line = line?;
+            let parse_error = |msg: String| ProfileError::Parse { line: idx + 1, msg };
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once(' ').unwrap_or((&line, ""));
+            let count = || {
+                value
+                    .parse::<u64>()
+                    .map_err(|e| parse_error(format!("invalid value '{value}' ({e})")))
+            };
+            match key {
+                "nb_dirs" => profile.nb_dirs = count()?,
+                "nb_files" => profile.nb_files = count()?,
+                "nb_binary_files" => profile.nb_binary_files = count()?,
+                "nb_empty_files" => profile.nb_empty_files = count()?,
+                "nb_empty_dirs" => profile.nb_empty_dirs = count()?,
+                "nb_symlinks" => profile.nb_symlinks = count()?,
+                "depth" => profile.depth = parse_histogram(value).map_err(parse_error)?,
+                "dir_fanout" => profile.dir_fanout = parse_histogram(value).map_err(parse_error)?,
+                "file_fanout" => {
+                    profile.file_fanout = parse_histogram(value).map_err(parse_error)?
+                }
+                "file_size" => profile.file_size = parse_histogram(value).map_err(parse_error)?,
+                "name_len" => profile.name_len = parse_histogram(value).map_err(parse_error)?,
+                "extension" => profile.extension = parse_histogram(value).map_err(parse_error)?,
+                _ => return Err(parse_error(format!("unknown key '{key}'"))),
+            }
+        }
+        Ok(profile)
+    }
+}
+
+fn write_histogram<K: Ord + std::fmt::Display>(
+    out: &mut dyn Write,
+    key: &str,
+    histogram: &Histogram<K>,
+) -> std::io::Result<()> {
+    write!(out, "{key}")?;
+    for (value, count) in histogram.iter() {
+        write!(out, " {value}:{count}")?;
+    }
+    writeln!(out)
+}
+
+fn parse_histogram<K>(value: &str) -> Result<Histogram<K>, String>
+where
+    K: Ord + std::str::FromStr,
+    <K as std::str::FromStr>::Err: std::fmt::Display,
+{
+    let mut histogram = Histogram(BTreeMap::new());
+    for pair in value.split_whitespace() {
+        let (key, count) = pair
+            .rsplit_once(':')
+            .ok_or(format!("'{pair}' is not a <value>:<count> pair"))?;
+        let key = key
+            .parse::<K>()
+            .map_err(|e| format!("'{key}' is not a valid value ({e})"))?;
+        let count = count
+            .parse::<u64>()
+            .map_err(|e| format!("'{count}' is not a valid count ({e})"))?;
+        histogram.0.insert(key, count);
+    }
+    Ok(histogram)
+}
@@ -0,0 +1,124 @@
+use std::io::{Result, Write};
+use std::time::{Duration, Instant};
+
+/// State of a running generation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressInfo {
+    pub entries: u64,
+    pub total_entries: u64,
+    /// Bytes written, or found already correct on disk.
+    pub bytes: u64,
+    pub total_bytes: u64,
+    pub elapsed: Duration,
+}
+
+impl ProgressInfo {
+    /// Bytes written per second.
+    pub fn rate(&self) -> f64 {
+        let elapsed = self.elapsed.as_secs_f64();
+        if elapsed > 0.0 {
+            self.bytes as f64 / elapsed
+        } else {
+            0.0
+        }
+    }
+
+    /// Estimated remaining time, from the rate so far.
+    pub fn eta(&self) -> Option<Duration> {
+        let done = if self.total_bytes > 0 {
+            self.bytes as f64 / self.total_bytes as f64
+        } else {
+            self.entries as f64 / self.total_entries.max(1) as f64
+        };
+        if done > 0.0 {
+            Some(self.elapsed.mul_f64((1.0 - done) / done))
+        } else {
+            None
+        }
+    }
+}
+
+/// Observer of a generation. `update` is called often (after each write), so
+/// implementations should throttle any costly output.
+///
+/// Closures taking a `&ProgressInfo` are observers updating on each call.
+pub trait Progress {
+    fn update(&mut self, info: &ProgressInfo);
+    /// Called once everything is written.
+    fn finish(&mut self, info: &ProgressInfo) {
+        self.update(info)
+    }
+}
+
+impl<F: FnMut(&ProgressInfo)> Progress for F {
+    fn update(&mut self, info: &ProgressInfo) {
+        self(info)
+    }
+}
+
+pub(crate) struct Tracker<'a> {
+    info: ProgressInfo,
+    start: Instant,
+    progress: &'a mut dyn Progress,
+}
+
+impl<'a> Tracker<'a> {
+    pub fn new(total_entries: u64, total_bytes: u64, progress: &'a mut dyn Progress) -> Self {
+        Self {
+            info: ProgressInfo {
+                entries: 0,
+                total_entries,
+                bytes: 0,
+                total_bytes,
+                elapsed: Duration::ZERO,
+            },
+            start: Instant::now(),
+            progress,
+        }
+    }
+
+    fn update(&mut self) {
+        self.info.elapsed = self.start.elapsed();
+        self.progress.update(&self.info);
+    }
+
+    pub fn entry_done(&mut self) {
+        self.info.entries += 1;
+        self.update();
+    }
+
+    /// Count `bytes` already on disk as done.
+    pub fn skip(&mut self, bytes: u64) {
+        self.info.bytes += bytes;
+        self.update();
+    }
+
+    pub fn finish(&mut self) -> ProgressInfo {
+        self.info.elapsed = self.start.elapsed();
+        self.progress.finish(&self.info);
+        self.info.clone()
+    }
+
+    /// Wrap `out` to report the bytes written to it.
+    pub fn writer<'t, W: Write>(&'t mut self, out: W) -> TrackedWrite<'t, 'a, W> {
+        TrackedWrite { out, tracker: self }
+    }
+}
+
+pub(crate) struct TrackedWrite<'t, 'a, W> {
+    out: W,
+    tracker: &'t mut Tracker<'a>,
+}
+
+impl<W: Write> Write for TrackedWrite<'_, '_, W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let written = self.out.write(buf)?;
+        self.tracker.info.bytes += written as u64;
+        self.tracker.update();
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.out.flush()
+    }
+}
@@ -1,23 +1,75 @@
+use super::config::Config;
+use super::profile::Profile;
+use super::rule::Rule;
 use rand::distributions::Alphanumeric;
 use rand::prelude::*;
 use std::cell::RefCell;
 use std::collections::VecDeque;
 use std::io::{Read, Result};
 use std::ops::{DerefMut, Range};
+use std::path::{Component, Path, PathBuf};
 use std::rc::Rc;
 
-pub struct BinRead(SmallRng);
+/// Version of the generation algorithm used by default.
+///
+/// The same options and seed always give the same tree and contents for a
+/// given version. Any change in the generated output gets a new version, older
+/// ones being kept reproducible:
+/// - 1: the first algorithm.
+/// - 2: binary contents are drawn by whole u64, a shorter file being a prefix of
+///   a longer one with the same seed. This changes the end of the binary files
+///   whose size modulo 8 is 1 to 4, all other files are the same as version 1.
+pub const GENERATOR_VERSION: u32 = 2;
+
+/// All the versions of the generation algorithm which can still be used.
+pub const GENERATOR_VERSIONS: std::ops::RangeInclusive<u32> = 1..=GENERATOR_VERSION;
+
+pub struct BinRead {
+    rng: SmallRng,
+    version: u32,
+    // Bytes of a random u64 not returned yet.
+    pending: [u8; 8],
+    pending_len: usize,
+}
 
 impl BinRead {
-    pub fn new(seed: u64) -> Self {
-        Self(SmallRng::seed_from_u64(seed))
+    pub fn new(seed: u64, version: u32) -> Self {
+        Self {
+            rng: SmallRng::seed_from_u64(seed),
+            version,
+            pending: [0; 8],
+            pending_len: 0,
+        }
     }
 }
 
 impl Read for BinRead {
+    // From version 2, bytes always come from whole u64 so the content does not
+    // depend on how it is read: a shorter file is a prefix of a longer one with
+    // the same seed. Version 1 reads the last 1 to 4 bytes from a u32 instead.
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
-        let to_read_len = std::cmp::min(buf.len(), 1024);
-        self.0.fill_bytes(&mut buf[..to_read_len]);
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.version == 1 {
+            // Version 1 content depends on the size of the reads.
+            let to_read_len = std::cmp::min(buf.len(), 1024);
+            self.rng.fill_bytes(&mut buf[..to_read_len]);
+            return Ok(to_read_len);
+        }
+        if self.pending_len == 0 && buf.len() >= 8 {
+            let to_read_len = std::cmp::min(buf.len(), 1024) & !7;
+            self.rng.fill_bytes(&mut buf[..to_read_len]);
+            return Ok(to_read_len);
+        }
+        if self.pending_len == 0 {
+            self.pending = self.rng.next_u64().to_le_bytes();
+            self.pending_len = 8;
+        }
+        let start = 8 - self.pending_len;
+        let to_read_len = std::cmp::min(buf.len(), self.pending_len);
+        buf[..to_read_len].copy_from_slice(&self.pending[start..start + to_read_len]);
+        self.pending_len -= to_read_len;
         Ok(to_read_len)
     }
 }
@@ -54,57 +106,139 @@ impl Read for TextRead {
     }
 }
 
-fn name(rng: &mut SmallRng) -> String {
-    (0..7).map(|_| rng.sample(Alphanumeric) as char).collect()
+const NAME_RETRIES: usize = 64;
+
+pub(crate) fn name(rng: &mut SmallRng, len: usize) -> String {
+    (0..len).map(|_| rng.sample(Alphanumeric) as char).collect()
 }
 
 #[derive(Debug)]
 pub struct Context {
+    /// Version of the generation algorithm.
+    pub version: u32,
     pub dir_depth: Range<u64>,
     pub nb_dir_child: Range<u64>,
     pub nb_file_child: Range<u64>,
     pub binary_ratio: f32,
+    pub dir_ratio: Option<f32>,
     pub file_len: Range<usize>,
+    pub profile: Option<Rc<Profile>>,
+    pub rules: Rc<Vec<Rule>>,
+    /// Depth of the directory being generated (the root is at depth 0).
+    pub depth: u64,
+    /// Path of the directory being generated, relative to the root.
+    pub path: PathBuf,
+    /// The rules matching the directory being generated, merged together.
+    level: Rule,
     pub rng: Rc<RefCell<SmallRng>>,
 }
 
 impl Context {
     pub fn nb_child(&self) -> (u64, u64) {
-        let nb_files = self.rng.borrow_mut().gen_range(self.nb_file_child.clone());
-        let can_contains_dir = self.rng.borrow_mut().gen_range(self.dir_depth.clone());
+        let mut rng = self.rng.borrow_mut();
+        let nb_files = match (&self.level.nb_file_child, &self.profile) {
+            (Some(r), _) => rng.gen_range(r.clone()),
+            (None, Some(p)) => p.file_fanout.sample(&mut rng).copied().unwrap_or(0),
+            (None, None) => rng.gen_range(self.nb_file_child.clone()),
+        };
+        // Directories forced by rules may go deeper than `dir_depth`.
+        let can_contains_dir = if self.dir_depth.is_empty() {
+            0
+        } else {
+            rng.gen_range(self.dir_depth.clone())
+        };
         let nb_dir = if can_contains_dir > 0 {
-            self.rng.borrow_mut().gen_range(self.nb_dir_child.clone())
+            match (&self.level.nb_dir_child, &self.profile) {
+                (Some(r), _) => rng.gen_range(r.clone()),
+                (None, Some(p)) => p.dir_fanout.sample(&mut rng).copied().unwrap_or(0),
+                (None, None) => rng.gen_range(self.nb_dir_child.clone()),
+            }
         } else {
             0
         };
-        (nb_files, nb_dir)
+        match self.level.dir_ratio.or(self.dir_ratio) {
+            None => (nb_files, nb_dir),
+            Some(_) if can_contains_dir == 0 => (nb_files, 0),
+            Some(dir_ratio) => {
+                // Keep the number of children but make each of them a directory
+                // with a `dir_ratio` probability.
+                let nb_children = nb_files + nb_dir;
+                let nb_dir = (0..nb_children)
+                    .filter(|_| rng.gen::<f32>() < dir_ratio)
+                    .count() as u64;
+                (nb_children - nb_dir, nb_dir)
+            }
+        }
+    }
+
+    /// Names of the directories to create in the current directory, on top
+    /// of the `nb_child` random ones.
+    pub fn dir_names(&self) -> &[String] {
+        &self.level.dir_names
     }
 
     pub fn is_binary(&self) -> bool {
-        self.rng.borrow_mut().gen::<f32>() <= self.binary_ratio
+        let binary_ratio = self.level.binary_ratio.unwrap_or(self.binary_ratio);
+        self.rng.borrow_mut().gen::<f32>() <= binary_ratio
     }
 
     pub fn file_len(&self) -> usize {
-        self.rng.borrow_mut().gen_range(self.file_len.clone())
+        match (&self.level.file_len, &self.profile) {
+            (Some(r), _) => self.rng.borrow_mut().gen_range(r.clone()),
+            (None, Some(p)) => p.sample_file_len(self.rng.borrow_mut().deref_mut()),
+            (None, None) => self.rng.borrow_mut().gen_range(self.file_len.clone()),
+        }
     }
 
     pub fn text_len(&self) -> usize {
-        self.rng.borrow_mut().gen_range(self.file_len.clone()) >> 3
+        match (&self.level.file_len, &self.profile) {
+            (Some(r), _) => self.rng.borrow_mut().gen_range(r.clone()) >> 3,
+            (None, Some(p)) => p.sample_file_len(self.rng.borrow_mut().deref_mut()),
+            (None, None) => self.rng.borrow_mut().gen_range(self.file_len.clone()) >> 3,
+        }
     }
 
     pub fn name(&self) -> String {
-        name(self.rng.borrow_mut().deref_mut())
+        self.name_with_retries(0)
+    }
+
+    /// A name for an entry when `retries` names drawn before were already
+    /// taken. Past `NAME_RETRIES` tries names get longer, so a profile with
+    /// only a few possible names cannot loop forever.
+    pub(crate) fn name_with_retries(&self, retries: usize) -> String {
+        let mut rng = self.rng.borrow_mut();
+        let len = match &self.profile {
+            Some(p) => p.sample_name_len(&mut rng),
+            None => 7,
+        };
+        name(&mut rng, len + retries / NAME_RETRIES)
     }
 
-    pub fn descent(&self) -> Self {
+    pub fn extension(&self, is_binary: bool) -> Option<String> {
+        match &self.profile {
+            Some(p) => p
+                .sample_extension(self.rng.borrow_mut().deref_mut())
+                .filter(|e| !e.is_empty()),
+            None => Some(if is_binary { "bin" } else { "text" }.into()),
+        }
+    }
+
+    pub fn descent(&self, name: &Path) -> Self {
         let dir_depth =
             self.dir_depth.start.saturating_sub(1)..self.dir_depth.end.saturating_sub(1);
         let rng = Rc::clone(&self.rng);
+        let depth = self.depth + 1;
+        let path = self.path.join(name);
         Self {
             dir_depth,
             nb_dir_child: self.nb_dir_child.clone(),
             nb_file_child: self.nb_file_child.clone(),
             file_len: self.file_len.clone(),
+            profile: self.profile.clone(),
+            rules: Rc::clone(&self.rules),
+            level: Rule::merge(&self.rules, depth, &path),
+            depth,
+            path,
             rng,
             ..*self
         }
@@ -118,27 +252,111 @@ impl Context {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildError {
+    EmptyRange { option: String, range: String },
+    InvalidRatio { option: String, value: f32 },
+    UnboundedDirNames { option: String },
+    InvalidDirName { option: String, name: String },
+    UnknownVersion(u32),
+}
+
+impl std::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::EmptyRange { option, range } => {
+                write!(f, "Range {range} of {option} is empty")
+            }
+            Self::InvalidRatio { option, value } => {
+                write!(f, "Ratio {value} of {option} is not between 0 and 1")
+            }
+            Self::UnboundedDirNames { option } => {
+                write!(
+                    f,
+                    "{option} is set without a depth, forced directories would nest forever"
+                )
+            }
+            Self::InvalidDirName { option, name } => {
+                write!(f, "{name:?} of {option} is not a single path component")
+            }
+            Self::UnknownVersion(version) => write!(
+                f,
+                "Unknown generator version {version} (supported versions are {} to {})",
+                GENERATOR_VERSIONS.start(),
+                GENERATOR_VERSIONS.end()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+fn check_range<T>(option: &str, range: &Option<Range<T>>) -> std::result::Result<(), BuildError>
+where
+    T: PartialOrd + std::fmt::Debug,
+{
+    match range {
+        Some(r) if r.is_empty() => Err(BuildError::EmptyRange {
+            option: option.into(),
+            range: format!("{r:?}"),
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn check_ratio(option: &str, ratio: Option<f32>) -> std::result::Result<(), BuildError> {
+    match ratio {
+        Some(value) if !(0.0..=1.0).contains(&value) => Err(BuildError::InvalidRatio {
+            option: option.into(),
+            value,
+        }),
+        _ => Ok(()),
+    }
+}
+
+// A forced directory name must stay in its parent: no `..`, `/` or empty name.
+fn is_dir_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(components.next(), Some(Component::Normal(c)) if c == name)
+        && components.next().is_none()
+}
+
 pub struct ContextBuilder {
+    version: u32,
     seed: u64,
     dir_depth: Range<u64>,
     nb_dir_child: Range<u64>,
     nb_file_child: Range<u64>,
     binary_ratio: f32,
+    dir_ratio: Option<f32>,
     file_len: Range<usize>,
+    profile: Option<Profile>,
+    rules: Vec<Rule>,
 }
 
 impl ContextBuilder {
     pub fn new() -> Self {
         Self {
+            version: GENERATOR_VERSION,
             seed: 0,
             dir_depth: 4..6,
             nb_dir_child: 0..5,
             nb_file_child: 0..10,
             binary_ratio: 0.2,
+            dir_ratio: None,
             file_len: 10..1_000_000,
+            profile: None,
+            rules: vec![],
         }
     }
 
+    /// Generate the tree with an older version of the generation algorithm.
+    /// Defaults to `GENERATOR_VERSION`.
+    pub fn version(&mut self, version: u32) -> &mut Self {
+        self.version = version;
+        self
+    }
+
     pub fn seed(&mut self, seed: u64) -> &mut Self {
         self.seed = seed;
         self
@@ -164,20 +382,109 @@ impl ContextBuilder {
         self
     }
 
+    /// Probability for a child entry to be a directory.
+    ///
+    /// When set, the number of children of a directory is drawn from
+    /// `nb_dir_child` and `nb_file_child` as usual, but each child is then a
+    /// directory with this probability. When unset, the number of files and
+    /// directories are drawn independently.
+    pub fn dir_ratio(&mut self, dir_ratio: f32) -> &mut Self {
+        self.dir_ratio = Some(dir_ratio);
+        self
+    }
+
     pub fn file_len(&mut self, file_len: Range<usize>) -> &mut Self {
         self.file_len = file_len;
         self
     }
 
-    pub fn create(self) -> Context {
-        Context {
+    /// Generate a tree looking like the one `profile` was scanned from.
+    /// Fan-outs, file sizes, names and extensions are sampled from the profile
+    /// and the depth and binary ratio are taken from it.
+    pub fn profile(&mut self, profile: Profile) -> &mut Self {
+        let max_depth = profile.max_depth();
+        self.dir_depth = max_depth..max_depth + 1;
+        self.binary_ratio = profile.binary_ratio();
+        self.profile = Some(profile);
+        self
+    }
+
+    /// Add a rule overriding options for some directories.
+    /// Rules are applied in the order they are added.
+    pub fn rule(&mut self, rule: Rule) -> &mut Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// The options of this builder, as they would be written in a config file.
+    /// A profile cannot be written back and is ignored.
+    pub fn to_config(&self) -> Config {
+        Config {
+            preset: None,
+            version: Some(self.version),
+            seed: Some(self.seed),
+            dir_depth: Some(self.dir_depth.clone()),
+            nb_dir_child: Some(self.nb_dir_child.clone()),
+            nb_file_child: Some(self.nb_file_child.clone()),
+            binary_ratio: Some(self.binary_ratio),
+            dir_ratio: self.dir_ratio,
+            file_len: Some(self.file_len.clone()),
+            profile: None,
+            rules: self.rules.clone(),
+        }
+    }
+
+    /// Check all the options, as `create` does.
+    pub fn validate(&self) -> std::result::Result<(), BuildError> {
+        if !GENERATOR_VERSIONS.contains(&self.version) {
+            return Err(BuildError::UnknownVersion(self.version));
+        }
+        check_range("dir_depth", &Some(self.dir_depth.clone()))?;
+        check_range("nb_dir_child", &Some(self.nb_dir_child.clone()))?;
+        check_range("nb_file_child", &Some(self.nb_file_child.clone()))?;
+        check_range("file_len", &Some(self.file_len.clone()))?;
+        check_ratio("binary_ratio", Some(self.binary_ratio))?;
+        check_ratio("dir_ratio", self.dir_ratio)?;
+        for (idx, rule) in self.rules.iter().enumerate() {
+            check_range(&format!("rule[{idx}].depth"), &rule.depth)?;
+            if !rule.dir_names.is_empty() && rule.depth.is_none() {
+                return Err(BuildError::UnboundedDirNames {
+                    option: format!("rule[{idx}].dir_names"),
+                });
+            }
+            if let Some(name) = rule.dir_names.iter().find(|n| !is_dir_name(n)) {
+                return Err(BuildError::InvalidDirName {
+                    option: format!("rule[{idx}].dir_names"),
+                    name: name.clone(),
+                });
+            }
+            check_range(&format!("rule[{idx}].nb_dir_child"), &rule.nb_dir_child)?;
+            check_range(&format!("rule[{idx}].nb_file_child"), &rule.nb_file_child)?;
+            check_range(&format!("rule[{idx}].file_len"), &rule.file_len)?;
+            check_ratio(&format!("rule[{idx}].binary_ratio"), rule.binary_ratio)?;
+            check_ratio(&format!("rule[{idx}].dir_ratio"), rule.dir_ratio)?;
+        }
+        Ok(())
+    }
+
+    /// Check all the options and create the context.
+    pub fn create(self) -> std::result::Result<Context, BuildError> {
+        self.validate()?;
+        Ok(Context {
+            version: self.version,
             dir_depth: self.dir_depth,
             nb_dir_child: self.nb_dir_child,
             nb_file_child: self.nb_file_child,
             binary_ratio: self.binary_ratio,
+            dir_ratio: self.dir_ratio,
             file_len: self.file_len,
+            profile: self.profile.map(Rc::new),
+            level: Rule::merge(&self.rules, 0, Path::new("")),
+            rules: Rc::new(self.rules),
+            depth: 0,
+            path: PathBuf::new(),
             rng: Rc::new(RefCell::new(SmallRng::seed_from_u64(self.seed))),
-        }
+        })
     }
 }
 
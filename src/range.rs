@@ -0,0 +1,172 @@
+use std::ops::Range;
+
+// Ranges are written `a..b` (`b` excluded), `a..=b` (`b` included) or `N`
+// (only `N`). Sizes may use a unit: `B`, `K`/`KB`/`KiB`, `M`/`MB`/`MiB`, ...
+// where `K`, `M`, ... are powers of 1024 and `KB`, `MB`, ... powers of 1000.
+
+const UNITS: &[&str] = &["K", "M", "G", "T"];
+
+fn parse_value<T>(s: &str) -> Result<T, String>
+where
+    T: std::str::FromStr,
+    <T as std::str::FromStr>::Err: std::fmt::Display,
+{
+    s.trim()
+        .parse::<T>()
+        .map_err(|e| format!("'{s}' is not a valid value ({e})"))
+}
+
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+    let value = parse_value::<u64>(value)?;
+    let multiplier = match unit.trim() {
+        "" | "B" => 1,
+        unit => {
+            let (prefix, base) = if let Some(prefix) = unit.strip_suffix("iB") {
+                (prefix, 1024)
+            } else if let Some(prefix) = unit.strip_suffix('B') {
+                (prefix, 1000)
+            } else {
+                (unit, 1024)
+            };
+            let exp = UNITS
+                .iter()
+                .position(|u| u.eq_ignore_ascii_case(prefix))
+                .ok_or(format!("'{unit}' is not a valid size unit"))?;
+            (base as u64).pow(exp as u32 + 1)
+        }
+    };
+    value
+        .checked_mul(multiplier)
+        .ok_or(format!("'{s}' is too big"))
+}
+
+pub fn format_size(size: u64) -> String {
+    for (base, suffix) in [(1024u64, "iB"), (1000, "B")] {
+        for (exp, unit) in UNITS.iter().enumerate().rev() {
+            let multiplier = base.pow(exp as u32 + 1);
+            if size != 0 && size.is_multiple_of(multiplier) {
+                return format!("{}{unit}{suffix}", size / multiplier);
+            }
+        }
+    }
+    size.to_string()
+}
+
+fn parse_range_with<T>(
+    s: &str,
+    parse: impl Fn(&str) -> Result<T, String>,
+    next: impl Fn(T) -> Option<T>,
+) -> Result<Range<T>, String>
+where
+    T: Copy,
+{
+    if let Some((start, end)) = s.split_once("..=") {
+        let end = parse(end)?;
+        Ok(parse(start)?..next(end).ok_or(format!("'{s}' is too big"))?)
+    } else if let Some((start, end)) = s.split_once("..") {
+        Ok(parse(start)?..parse(end)?)
+    } else {
+        let value = parse(s)?;
+        Ok(value..next(value).ok_or(format!("'{s}' is too big"))?)
+    }
+}
+
+fn format_range_with<T>(
+    range: &Range<T>,
+    format: impl Fn(T) -> String,
+    next: impl Fn(T) -> Option<T>,
+) -> String
+where
+    T: Copy + PartialEq,
+{
+    if next(range.start) == Some(range.end) {
+        format(range.start)
+    } else {
+        format!("{}..{}", format(range.start), format(range.end))
+    }
+}
+
+pub fn parse_range(s: &str) -> Result<Range<u64>, String> {
+    parse_range_with(s, parse_value, |v: u64| v.checked_add(1))
+}
+
+pub fn format_range(range: &Range<u64>) -> String {
+    format_range_with(range, |v| v.to_string(), |v| v.checked_add(1))
+}
+
+pub fn parse_size_range(s: &str) -> Result<Range<usize>, String> {
+    let parse = |s: &str| {
+        let size = parse_size(s)?;
+        usize::try_from(size).map_err(|_| format!("'{s}' is too big"))
+    };
+    parse_range_with(s, parse, |v: usize| v.checked_add(1))
+}
+
+pub fn format_size_range(range: &Range<usize>) -> String {
+    let has_unit = |v: usize| v != 0 && format_size(v as u64) != v.to_string();
+    // `4KiB..=2GiB` is easier to read than `4KiB..2147483649`.
+    let longer = range.start.checked_add(1).is_some_and(|v| range.end > v);
+    if longer && !has_unit(range.end) && has_unit(range.end - 1) {
+        format!(
+            "{}..={}",
+            format_size(range.start as u64),
+            format_size(range.end as u64 - 1)
+        )
+    } else {
+        format_range_with(range, |v| format_size(v as u64), |v| v.checked_add(1))
+    }
+}
+
+// Serde helpers to write ranges with the same syntax than on the command line
+// rather than as serde's `{ start, end }` maps.
+
+pub(crate) mod range_str {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::ops::Range;
+
+    pub fn serialize<S>(range: &Option<Range<u64>>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match range {
+            Some(r) => serializer.serialize_some(&super::format_range(r)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Range<u64>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| super::parse_range(&s).map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+pub(crate) mod size_range_str {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::ops::Range;
+
+    pub fn serialize<S>(range: &Option<Range<usize>>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match range {
+            Some(r) => serializer.serialize_some(&super::format_size_range(r)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Range<usize>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| super::parse_size_range(&s).map_err(D::Error::custom))
+            .transpose()
+    }
+}
@@ -0,0 +1,67 @@
+use super::glob::Pattern;
+use super::range::{range_str, size_range_str};
+
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::path::Path;
+
+/// Override generation options for the directories matching a depth and/or a path.
+///
+/// All rules matching a directory are applied in order, later rules taking
+/// precedence. A rule without `depth` and `path` matches all directories.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rule {
+    /// Depth of the matching directories (the root is at depth 0).
+    #[serde(skip_serializing_if = "Option::is_none", with = "range_str")]
+    pub depth: Option<Range<u64>>,
+    /// Glob pattern on the path of the matching directories, relative to the root.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<Pattern>,
+    /// Directories always created, on top of the random ones.
+    /// Needs a `depth`: forced directories could match the rule again.
+    /// Names are single path components, without `/`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dir_names: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none", with = "range_str")]
+    pub nb_dir_child: Option<Range<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none", with = "range_str")]
+    pub nb_file_child: Option<Range<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub binary_ratio: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dir_ratio: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none", with = "size_range_str")]
+    pub file_len: Option<Range<usize>>,
+}
+
+impl Rule {
+    pub fn matches(&self, depth: u64, path: &Path) -> bool {
+        self.depth.as_ref().is_none_or(|d| d.contains(&depth))
+            && self.path.as_ref().is_none_or(|p| p.matches(path))
+    }
+
+    /// Merge all the `rules` matching a directory into one.
+    pub(crate) fn merge(rules: &[Rule], depth: u64, path: &Path) -> Self {
+        let mut merged = Self::default();
+        for rule in rules.iter().filter(|r| r.matches(depth, path)) {
+            merged.dir_names.extend(rule.dir_names.iter().cloned());
+            if rule.nb_dir_child.is_some() {
+                merged.nb_dir_child = rule.nb_dir_child.clone();
+            }
+            if rule.nb_file_child.is_some() {
+                merged.nb_file_child = rule.nb_file_child.clone();
+            }
+            if rule.binary_ratio.is_some() {
+                merged.binary_ratio = rule.binary_ratio;
+            }
+            if rule.dir_ratio.is_some() {
+                merged.dir_ratio = rule.dir_ratio;
+            }
+            if rule.file_len.is_some() {
+                merged.file_len = rule.file_len.clone();
+            }
+        }
+        merged
+    }
+}
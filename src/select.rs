@@ -0,0 +1,45 @@
+use super::glob::Pattern;
+use super::tree::DirEntry;
+
+use std::path::Path;
+
+impl DirEntry {
+    /// Build the part of the tree matching one of `patterns`: matching entries
+    /// (with all their content for directories) and the directories leading
+    /// to them. A plain path selects a single subtree.
+    ///
+    /// Paths are kept, so the result can be generated or exported as is.
+    /// Inodes are renumbered.
+    pub fn select(&self, patterns: &[Pattern]) -> Self {
+        let mut root = self
+            .select_in(Path::new(""), patterns)
+            .unwrap_or_else(|| Self::empty(self.name.clone()));
+        root.renumber(1, 1);
+        root
+    }
+
+    /// The selected part of `self` (at `path`), `None` if nothing matches.
+    fn select_in(&self, path: &Path, patterns: &[Pattern]) -> Option<Self> {
+        let matches = |path: &Path| patterns.iter().any(|p| p.matches(path));
+        let mut selected = Self::empty(self.name.clone());
+        for dir in &self.dirs {
+            let dir_path = path.join(&dir.name);
+            if matches(&dir_path) {
+                selected.dirs.push(dir.clone());
+            } else if let Some(dir) = dir.select_in(&dir_path, patterns) {
+                selected.dirs.push(dir);
+            }
+        }
+        selected.files = self
+            .files
+            .iter()
+            .filter(|f| matches(&path.join(&f.name)))
+            .cloned()
+            .collect();
+        if selected.get_nb_children() == 0 {
+            None
+        } else {
+            Some(selected)
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use super::tree::DirEntry;
+
+use std::io::{Read, Write};
+
+// With the `serde` feature, `DirEntry` and `FileEntry` implement `Serialize`
+// and `Deserialize`. These helpers read and write the whole tree, inodes and
+// seeds included, as JSON or CBOR.
+
+#[derive(Debug)]
+pub enum SerializeError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Cbor(String),
+}
+
+impl std::fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Cannot read or write tree: {e}"),
+            Self::Json(e) => write!(f, "Invalid JSON tree: {e}"),
+            Self::Cbor(e) => write!(f, "Invalid CBOR tree: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for SerializeError {}
+
+impl From<std::io::Error> for SerializeError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl DirEntry {
+    pub fn write_json(&self, out: &mut dyn Write) -> Result<(), SerializeError> {
+        serde_json::to_writer(&mut *out, self).map_err(SerializeError::Json)?;
+        Ok(writeln!(out)?)
+    }
+
+    pub fn from_json(input: &mut dyn Read) -> Result<Self, SerializeError> {
+        serde_json::from_reader(input).map_err(SerializeError::Json)
+    }
+
+    pub fn write_cbor(&self, out: &mut dyn Write) -> Result<(), SerializeError> {
+        ciborium::into_writer(self, out).map_err(|e| SerializeError::Cbor(e.to_string()))
+    }
+
+    pub fn from_cbor(input: &mut dyn Read) -> Result<Self, SerializeError> {
+        ciborium::from_reader(input).map_err(|e| SerializeError::Cbor(e.to_string()))
+    }
+}
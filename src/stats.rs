@@ -0,0 +1,174 @@
+use super::profile::{size_bucket, Histogram};
+use super::tree::{DirEntry, FileEntry};
+use super::walk::Visitor;
+
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Statistics of a tree, computed from the model without generating anything.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct Stats {
+    /// Number of directories, root included.
+    pub nb_dirs: u64,
+    pub nb_empty_dirs: u64,
+    pub nb_files: u64,
+    pub nb_text_files: u64,
+    pub nb_binary_files: u64,
+    pub nb_empty_files: u64,
+    pub size: u64,
+    pub text_size: u64,
+    pub binary_size: u64,
+    /// Number of directories per depth (root is at depth 0).
+    pub depth: Histogram<u64>,
+    /// Number of sub-directories per directory.
+    pub dir_fanout: Histogram<u64>,
+    /// Number of files per directory.
+    pub file_fanout: Histogram<u64>,
+    /// File sizes, bucketed by power of two.
+    pub file_size: Histogram<u64>,
+    pub longest_path: Option<PathBuf>,
+    /// The directory with the most children.
+    pub largest_dir: Option<PathBuf>,
+    pub largest_dir_children: u64,
+    /// Non empty files with the same content as another file.
+    pub nb_duplicates: u64,
+    pub duplicate_size: u64,
+}
+
+impl Stats {
+    pub fn binary_ratio(&self) -> f32 {
+        if self.nb_files == 0 {
+            0.0
+        } else {
+            self.nb_binary_files as f32 / self.nb_files as f32
+        }
+    }
+}
+
+struct StatsVisitor {
+    stats: Stats,
+    // Content of a file is fully defined by its kind, seed, size and
+    // generator version.
+    contents: HashSet<(bool, u64, usize, u32)>,
+}
+
+impl Visitor for StatsVisitor {
+    fn enter_dir(&mut self, path: &Path, dir: &DirEntry) {
+        let stats = &mut self.stats;
+        let nb_children = dir.get_nb_children() as u64;
+        stats.nb_dirs += 1;
+        if nb_children == 0 {
+            stats.nb_empty_dirs += 1;
+        }
+        stats.depth.add(path.components().count() as u64);
+        stats.dir_fanout.add(dir.dirs().len() as u64);
+        stats.file_fanout.add(dir.files().len() as u64);
+        if stats.largest_dir.is_none() || nb_children > stats.largest_dir_children {
+            stats.largest_dir = Some(path.to_path_buf());
+            stats.largest_dir_children = nb_children;
+        }
+        self.add_path(path);
+    }
+
+    fn visit_file(&mut self, path: &Path, file: &FileEntry) {
+        let stats = &mut self.stats;
+        let size = file.size() as u64;
+        stats.nb_files += 1;
+        stats.size += size;
+        if file.is_binary {
+            stats.nb_binary_files += 1;
+            stats.binary_size += size;
+        } else {
+            stats.nb_text_files += 1;
+            stats.text_size += size;
+        }
+        stats.file_size.add(size_bucket(size));
+        if size == 0 {
+            stats.nb_empty_files += 1;
+        } else if !self.contents.insert((
+            file.is_binary,
+            file.seed,
+            file.size,
+            file.content_version(),
+        )) {
+            stats.nb_duplicates += 1;
+            stats.duplicate_size += size;
+        }
+        self.add_path(path);
+    }
+}
+
+impl StatsVisitor {
+    fn add_path(&mut self, path: &Path) {
+        let len = path.as_os_str().len();
+        if self
+            .stats
+            .longest_path
+            .as_ref()
+            .is_none_or(|p| len > p.as_os_str().len())
+        {
+            self.stats.longest_path = Some(path.to_path_buf());
+        }
+    }
+}
+
+impl DirEntry {
+    pub fn stats(&self) -> Stats {
+        let mut visitor = StatsVisitor {
+            stats: Stats::default(),
+            contents: HashSet::new(),
+        };
+        self.visit(&mut visitor);
+        visitor.stats
+    }
+}
+
+impl std::fmt::Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Directories: {} ({} empty)",
+            self.nb_dirs, self.nb_empty_dirs
+        )?;
+        writeln!(
+            f,
+            "Files: {} ({} text, {} binary, {} empty), binary ratio {:.1}%",
+            self.nb_files,
+            self.nb_text_files,
+            self.nb_binary_files,
+            self.nb_empty_files,
+            self.binary_ratio() * 100.0
+        )?;
+        writeln!(
+            f,
+            "Size: {} bytes ({} text, {} binary)",
+            self.size, self.text_size, self.binary_size
+        )?;
+        if let Some(path) = &self.longest_path {
+            writeln!(
+                f,
+                "Longest path: {} ({} bytes)",
+                path.display(),
+                path.as_os_str().len()
+            )?;
+        }
+        if let Some(path) = &self.largest_dir {
+            writeln!(
+                f,
+                "Largest directory: /{} ({} children)",
+                path.display(),
+                self.largest_dir_children
+            )?;
+        }
+        writeln!(
+            f,
+            "Duplicates: {} files ({} bytes)",
+            self.nb_duplicates, self.duplicate_size
+        )?;
+        writeln!(f, "Depth: {}", self.depth)?;
+        writeln!(f, "Directory fan-out: {}", self.dir_fanout)?;
+        writeln!(f, "File fan-out: {}", self.file_fanout)?;
+        write!(f, "File size: {}", self.file_size)
+    }
+}
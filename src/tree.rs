@@ -1,8 +1,13 @@
 use super::random::{BinRead, Context, TextRead};
 
-use std::fs::create_dir;
+use std::collections::HashSet;
 use std::io::{Read, Result};
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
+
+// Metadata shared by all entries, the tree model has no per entry metadata.
+pub const PERM: u16 = 0o555;
+pub const UID: u32 = 1000;
+pub const GID: u32 = 1000;
 
 #[derive(Debug)]
 pub struct NoEntry;
@@ -15,28 +20,41 @@ impl std::fmt::Display for NoEntry {
 
 impl std::error::Error for NoEntry {}
 
+#[derive(Clone, Copy)]
 pub enum EntryRef<'a> {
     File(&'a FileEntry),
     Dir(&'a DirEntry),
 }
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FileEntry {
     pub name: PathBuf,
     pub ino: u64,
     pub parent_ino: u64,
-    seed: u64,
-    is_binary: bool,
-    size: usize,
+    pub(crate) seed: u64,
+    pub(crate) is_binary: bool,
+    pub(crate) size: usize,
+    /// Version of the algorithm generating the content.
+    /// Trees serialized before versioning use version 1.
+    #[cfg_attr(feature = "serde", serde(default = "first_version"))]
+    pub(crate) version: u32,
+}
+
+#[cfg(feature = "serde")]
+fn first_version() -> u32 {
+    1
 }
 
 impl FileEntry {
-    fn new(
+    pub(crate) fn new(
         name: PathBuf,
         ino: u64,
         parent_ino: u64,
         seed: u64,
         is_binary: bool,
         size: usize,
+        version: u32,
     ) -> Self {
         Self {
             ino,
@@ -45,33 +63,39 @@ impl FileEntry {
             seed,
             is_binary,
             size,
+            version,
         }
     }
 
     pub fn get_reader(&self) -> Box<dyn Read> {
         if self.is_binary {
-            Box::new(BinRead::new(self.seed).take(self.size as u64))
+            Box::new(BinRead::new(self.seed, self.version).take(self.size as u64))
         } else {
             Box::new(TextRead::new(self.seed).take(self.size as u64))
         }
     }
 
-    fn generate(&self, dir: &Path) -> Result<()> {
-        let path = dir.join(&self.name);
-        let mut file = std::fs::OpenOptions::new()
-            .write(true)
-            .create_new(true)
-            .open(path)?;
-        //println!("Generate files with {} bytes", self.size);
-        std::io::copy(&mut self.get_reader(), &mut file)?;
-        Ok(())
+    pub fn size(&self) -> usize {
+        self.size
     }
 
-    fn size(&self) -> usize {
-        self.size
+    /// First version generating the same content, text contents being the
+    /// same in all versions.
+    pub(crate) fn content_version(&self) -> u32 {
+        if self.is_binary {
+            self.version
+        } else {
+            1
+        }
     }
 
-    fn get_entry(&self, ino: u64) -> std::result::Result<EntryRef, NoEntry> {
+    /// Whether the content is a prefix of the content of the same file with a
+    /// bigger size. Version 1 binary contents depend on the read sizes.
+    pub(crate) fn prefix_stable(&self) -> bool {
+        !self.is_binary || self.version >= 2
+    }
+
+    fn get_entry(&self, ino: u64) -> std::result::Result<EntryRef<'_>, NoEntry> {
         if ino == self.ino {
             Ok(EntryRef::File(self))
         } else {
@@ -90,10 +114,10 @@ impl FileEntry {
             mtime: std::time::UNIX_EPOCH,
             ctime: std::time::UNIX_EPOCH,
             crtime: std::time::UNIX_EPOCH,
-            perm: 0o555,
+            perm: PERM,
             nlink: 2,
-            uid: 1000,
-            gid: 1000,
+            uid: UID,
+            gid: GID,
             rdev: 0,
             blksize: 0,
             flags: 0,
@@ -101,12 +125,14 @@ impl FileEntry {
     }
 }
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DirEntry {
     pub name: PathBuf,
     pub ino: u64,
     pub parent_ino: u64,
-    files: Vec<FileEntry>,
-    dirs: Vec<DirEntry>,
+    pub(crate) files: Vec<FileEntry>,
+    pub(crate) dirs: Vec<DirEntry>,
 }
 
 impl DirEntry {
@@ -117,40 +143,63 @@ impl DirEntry {
     pub(crate) fn new(name: PathBuf, ino: u64, parent_ino: u64, context: Context) -> (u64, Self) {
         let mut current_ino = ino;
         let (nb_files, nb_dir) = context.nb_child();
-        let dirs = (0..nb_dir)
-            .map(|_| {
-                let child_name: PathBuf = context.name().into();
-                let (last_ino, d) =
-                    DirEntry::new(child_name, current_ino + 1, ino, context.descent());
+        let mut names = HashSet::new();
+        let forced_names = context
+            .dir_names()
+            .iter()
+            .map(PathBuf::from)
+            .filter(|n| names.insert(n.clone()))
+            .collect::<Vec<_>>();
+        let random_names = (0..nb_dir).map(|_| {
+            (0..)
+                .map(|retries| PathBuf::from(context.name_with_retries(retries)))
+                .find(|n| names.insert(n.clone()))
+                .unwrap()
+        });
+        let dirs = forced_names
+            .into_iter()
+            .chain(random_names)
+            .map(|child_name| {
+                let (last_ino, d) = DirEntry::new(
+                    child_name.clone(),
+                    current_ino + 1,
+                    ino,
+                    context.descent(&child_name),
+                );
                 current_ino = last_ino;
                 d
             })
             .collect();
         let files = (0..nb_files)
             .map(|_| {
-                let mut child_name: PathBuf = context.name().into();
+                let mut retries = 0;
+                let (child_name, is_binary) = loop {
+                    let mut child_name: PathBuf = context.name_with_retries(retries).into();
+                    retries += 1;
+                    let is_binary = context.is_binary();
+                    if let Some(extension) = context.extension(is_binary) {
+                        child_name.set_extension(extension);
+                    }
+                    if names.insert(child_name.clone()) {
+                        break (child_name, is_binary);
+                    }
+                };
                 current_ino += 1;
-                if context.is_binary() {
-                    child_name.set_extension("bin");
-                    FileEntry::new(
-                        child_name,
-                        current_ino,
-                        ino,
-                        context.get(),
-                        true,
-                        context.file_len(),
-                    )
+                let seed = context.get();
+                let size = if is_binary {
+                    context.file_len()
                 } else {
-                    child_name.set_extension("text");
-                    FileEntry::new(
-                        child_name,
-                        current_ino,
-                        ino,
-                        context.get(),
-                        false,
-                        context.text_len(),
-                    )
-                }
+                    context.text_len()
+                };
+                FileEntry::new(
+                    child_name,
+                    current_ino,
+                    ino,
+                    seed,
+                    is_binary,
+                    size,
+                    context.version,
+                )
             })
             .collect();
         (
@@ -165,14 +214,39 @@ impl DirEntry {
         )
     }
 
-    pub fn generate(&self, dir: &Path) -> Result<()> {
-        let path = dir.join(&self.name);
-        create_dir(&path)?;
-        for dir in &self.dirs {
-            dir.generate(&path)?;
+    pub(crate) fn empty(name: PathBuf) -> Self {
+        Self {
+            name,
+            ino: 0,
+            parent_ino: 0,
+            files: vec![],
+            dirs: vec![],
         }
-        for file in &self.files {
-            file.generate(&path)?;
+    }
+
+    /// Reassign inodes in the same order `new` allocates them.
+    /// Returns the last inode used.
+    pub(crate) fn renumber(&mut self, ino: u64, parent_ino: u64) -> u64 {
+        self.ino = ino;
+        self.parent_ino = parent_ino;
+        let mut current_ino = ino;
+        for dir in &mut self.dirs {
+            current_ino = dir.renumber(current_ino + 1, ino);
+        }
+        for file in &mut self.files {
+            current_ino += 1;
+            file.ino = current_ino;
+            file.parent_ino = ino;
+        }
+        current_ino
+    }
+
+    /// Visit all the entries below this directory in the order `generate` creates
+    /// them: each directory before its content, sub-directories before files.
+    /// Paths given to `visit` are relative to `self`.
+    pub(crate) fn walk(&self, visit: &mut dyn FnMut(&Path, EntryRef) -> Result<()>) -> Result<()> {
+        for (path, entry) in self.iter_dfs() {
+            visit(&path, entry)?;
         }
         Ok(())
     }
@@ -188,7 +262,7 @@ impl DirEntry {
         file_size + dir_size
     }
 
-    pub fn get_entry(&self, ino: u64) -> std::result::Result<EntryRef, NoEntry> {
+    pub fn get_entry(&self, ino: u64) -> std::result::Result<EntryRef<'_>, NoEntry> {
         if ino == self.ino {
             Ok(EntryRef::Dir(self))
         } else {
@@ -206,7 +280,7 @@ impl DirEntry {
         }
     }
 
-    pub fn get_child(&self, name: &Path) -> std::result::Result<EntryRef, NoEntry> {
+    pub fn get_child(&self, name: &Path) -> std::result::Result<EntryRef<'_>, NoEntry> {
         for file in &self.files {
             if file.name == name {
                 return Ok(EntryRef::File(file));
@@ -220,7 +294,21 @@ impl DirEntry {
         Err(NoEntry)
     }
 
-    pub fn get_child_idx(&self, mut idx: usize) -> std::result::Result<EntryRef, NoEntry> {
+    /// Look up an entry by its path relative to `self`, an empty path being
+    /// `self`.
+    pub fn get_path(&self, path: &Path) -> std::result::Result<EntryRef<'_>, NoEntry> {
+        let mut entry = EntryRef::Dir(self);
+        for component in path.components() {
+            entry = match (component, entry) {
+                (Component::CurDir, entry) => entry,
+                (Component::Normal(name), EntryRef::Dir(dir)) => dir.get_child(Path::new(name))?,
+                _ => return Err(NoEntry),
+            };
+        }
+        Ok(entry)
+    }
+
+    pub fn get_child_idx(&self, mut idx: usize) -> std::result::Result<EntryRef<'_>, NoEntry> {
         if idx < self.files.len() {
             Ok(EntryRef::File(&self.files[idx]))
         } else {
@@ -248,10 +336,10 @@ impl DirEntry {
             mtime: std::time::UNIX_EPOCH,
             ctime: std::time::UNIX_EPOCH,
             crtime: std::time::UNIX_EPOCH,
-            perm: 0o555,
+            perm: PERM,
             nlink: 2,
-            uid: 1000,
-            gid: 1000,
+            uid: UID,
+            gid: GID,
             rdev: 0,
             blksize: 0,
             flags: 0,
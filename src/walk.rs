@@ -0,0 +1,103 @@
+use super::tree::{DirEntry, EntryRef, FileEntry};
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+
+fn children<'a>(path: &Path, dir: &'a DirEntry) -> Vec<(PathBuf, EntryRef<'a>)> {
+    let dirs = dir
+        .dirs
+        .iter()
+        .map(|d| (path.join(&d.name), EntryRef::Dir(d)));
+    let files = dir
+        .files
+        .iter()
+        .map(|f| (path.join(&f.name), EntryRef::File(f)));
+    dirs.chain(files).collect()
+}
+
+/// Depth first iterator over the entries of a tree, in the order `generate`
+/// creates them: each directory before its content, sub-directories before
+/// files. Paths are relative to the root, which is not yielded.
+pub struct DepthFirst<'a> {
+    stack: Vec<(PathBuf, EntryRef<'a>)>,
+}
+
+impl<'a> Iterator for DepthFirst<'a> {
+    type Item = (PathBuf, EntryRef<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, entry) = self.stack.pop()?;
+        if let EntryRef::Dir(dir) = entry {
+            self.stack.extend(children(&path, dir).into_iter().rev());
+        }
+        Some((path, entry))
+    }
+}
+
+/// Breadth first iterator over the entries of a tree: all the entries at
+/// depth 1, then all at depth 2... Paths are relative to the root, which is
+/// not yielded.
+pub struct BreadthFirst<'a> {
+    queue: VecDeque<(PathBuf, EntryRef<'a>)>,
+}
+
+impl<'a> Iterator for BreadthFirst<'a> {
+    type Item = (PathBuf, EntryRef<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, entry) = self.queue.pop_front()?;
+        if let EntryRef::Dir(dir) = entry {
+            self.queue.extend(children(&path, dir));
+        }
+        Some((path, entry))
+    }
+}
+
+/// Visitor of a tree, see `DirEntry::visit`. All methods do nothing by
+/// default.
+pub trait Visitor {
+    /// Called on a directory, before its content.
+    fn enter_dir(&mut self, _path: &Path, _dir: &DirEntry) {}
+    /// Called on a directory, after its content.
+    fn leave_dir(&mut self, _path: &Path, _dir: &DirEntry) {}
+    fn visit_file(&mut self, _path: &Path, _file: &FileEntry) {}
+}
+
+impl DirEntry {
+    pub fn dirs(&self) -> &[DirEntry] {
+        &self.dirs
+    }
+
+    pub fn files(&self) -> &[FileEntry] {
+        &self.files
+    }
+
+    pub fn iter_dfs(&self) -> DepthFirst<'_> {
+        DepthFirst {
+            stack: children(Path::new(""), self).into_iter().rev().collect(),
+        }
+    }
+
+    pub fn iter_bfs(&self) -> BreadthFirst<'_> {
+        BreadthFirst {
+            queue: children(Path::new(""), self).into(),
+        }
+    }
+
+    /// Walk the tree depth first (in the `iter_dfs` order), calling `visitor`
+    /// on every entry. The root itself is entered and left with an empty path.
+    pub fn visit(&self, visitor: &mut dyn Visitor) {
+        self.visit_in(Path::new(""), visitor)
+    }
+
+    fn visit_in(&self, path: &Path, visitor: &mut dyn Visitor) {
+        visitor.enter_dir(path, self);
+        for dir in &self.dirs {
+            dir.visit_in(&path.join(&dir.name), visitor);
+        }
+        for file in &self.files {
+            visitor.visit_file(&path.join(&file.name), file);
+        }
+        visitor.leave_dir(path, self);
+    }
+}
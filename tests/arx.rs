@@ -0,0 +1,59 @@
+#![cfg(feature = "arx")]
+
+use arx_test_dir::{ArxWriter, ContextBuilder, DirEntry};
+use std::path::Path;
+
+fn build_tree(seed: u64) -> DirEntry {
+    let mut builder = ContextBuilder::new();
+    builder
+        .seed(seed)
+        .dir_depth(1..3)
+        .nb_dir_child(1..4)
+        .nb_file_child(1..6)
+        .file_len(0..50_000);
+    DirEntry::new_root(builder.create().unwrap())
+}
+
+/// Check `actual` has exactly the same entries and file contents as `expected`.
+fn assert_same_dir(expected: &Path, actual: &Path) {
+    let names = |path: &Path| {
+        let mut names = std::fs::read_dir(path)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    };
+    let expected_names = names(expected);
+    assert_eq!(expected_names, names(actual), "in {actual:?}");
+    for name in expected_names {
+        let (expected, actual) = (expected.join(&name), actual.join(&name));
+        if expected.is_dir() {
+            assert!(actual.is_dir(), "{actual:?} is not a directory");
+            assert_same_dir(&expected, &actual);
+        } else {
+            let content = std::fs::read(&expected).unwrap();
+            assert!(
+                content == std::fs::read(&actual).unwrap(),
+                "{actual:?} differs"
+            );
+        }
+    }
+}
+
+#[test]
+fn arx_roundtrip() {
+    let tmp = tempfile::tempdir().unwrap();
+    let generated = tmp.path().join("generated");
+    let extracted = tmp.path().join("extracted");
+    let archive = tmp.path().join("tree.arx");
+
+    let tree = build_tree(42);
+    assert!(tree.nb_files() > 0);
+    tree.generate(&generated).unwrap();
+    tree.export(&mut ArxWriter::new(&archive).unwrap()).unwrap();
+
+    std::fs::create_dir(&extracted).unwrap();
+    libarx::extract_all(&archive, &extracted, false, libarx::Overwrite::Error).unwrap();
+    assert_same_dir(&generated, &extracted);
+}
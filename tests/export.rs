@@ -0,0 +1,140 @@
+use arx_test_dir::{ContextBuilder, CpioWriter, DirEntry, TarWriter, ZipMethod, ZipWriter};
+use std::io::Read;
+use std::path::Path;
+
+// Deep enough to get paths longer than the 100 bytes of a ustar name field.
+fn build_tree(seed: u64) -> DirEntry {
+    let mut builder = ContextBuilder::new();
+    builder
+        .seed(seed)
+        .dir_depth(14..15)
+        .nb_dir_child(1..2)
+        .nb_file_child(1..4)
+        .file_len(0..50_000);
+    let tree = DirEntry::new_root(builder.create().unwrap());
+    assert!(tree
+        .iter_dfs()
+        .any(|(path, _)| path.as_os_str().len() > 100));
+    tree
+}
+
+/// Check `actual` has exactly the same entries and file contents as `expected`.
+fn assert_same_dir(expected: &Path, actual: &Path) {
+    let names = |path: &Path| {
+        let mut names = std::fs::read_dir(path)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    };
+    let expected_names = names(expected);
+    assert_eq!(expected_names, names(actual), "in {actual:?}");
+    for name in expected_names {
+        let (expected, actual) = (expected.join(&name), actual.join(&name));
+        if expected.is_dir() {
+            assert!(actual.is_dir(), "{actual:?} is not a directory");
+            assert_same_dir(&expected, &actual);
+        } else {
+            let content = std::fs::read(&expected).unwrap();
+            assert!(
+                content == std::fs::read(&actual).unwrap(),
+                "{actual:?} differs"
+            );
+        }
+    }
+}
+
+fn hex_field(header: &[u8], idx: usize) -> usize {
+    let field = std::str::from_utf8(&header[6 + idx * 8..6 + (idx + 1) * 8]).unwrap();
+    usize::from_str_radix(field, 16).unwrap()
+}
+
+/// Extract a newc cpio archive in `dir`.
+fn extract_cpio(mut archive: &[u8], dir: &Path) {
+    let pad = |len: usize| (4 - len % 4) % 4;
+    loop {
+        let (header, rest) = archive.split_at(110);
+        assert_eq!(&header[..6], b"070701");
+        let mode = hex_field(header, 1);
+        let file_len = hex_field(header, 6);
+        let name_len = hex_field(header, 11);
+        let name = std::str::from_utf8(&rest[..name_len - 1]).unwrap();
+        let rest = &rest[name_len + pad(110 + name_len)..];
+        if name == "TRAILER!!!" {
+            return;
+        }
+        let path = dir.join(name);
+        if mode & 0o170000 == 0o040000 {
+            std::fs::create_dir(path).unwrap();
+        } else {
+            std::fs::write(path, &rest[..file_len]).unwrap();
+        }
+        archive = &rest[file_len + pad(file_len)..];
+    }
+}
+
+#[test]
+fn tar_roundtrip() {
+    let tmp = tempfile::tempdir().unwrap();
+    let generated = tmp.path().join("generated");
+    let extracted = tmp.path().join("extracted");
+
+    let tree = build_tree(42);
+    tree.generate(&generated).unwrap();
+    let mut writer = TarWriter::new(vec![]);
+    tree.export(&mut writer).unwrap();
+    let archive = writer.into_inner();
+
+    tar::Archive::new(archive.as_slice())
+        .unpack(&extracted)
+        .unwrap();
+    assert_same_dir(&generated, &extracted);
+}
+
+#[test]
+fn zip_roundtrip() {
+    for method in [ZipMethod::Stored, ZipMethod::Deflate] {
+        let tmp = tempfile::tempdir().unwrap();
+        let generated = tmp.path().join("generated");
+        let extracted = tmp.path().join("extracted");
+
+        let tree = build_tree(42);
+        tree.generate(&generated).unwrap();
+        let mut writer = ZipWriter::new(vec![], method);
+        tree.export(&mut writer).unwrap();
+        let archive = writer.into_inner();
+
+        let mut zip = zip::ZipArchive::new(std::io::Cursor::new(archive)).unwrap();
+        std::fs::create_dir(&extracted).unwrap();
+        for idx in 0..zip.len() {
+            let mut entry = zip.by_index(idx).unwrap();
+            let path = extracted.join(entry.enclosed_name().unwrap());
+            if entry.is_dir() {
+                std::fs::create_dir(path).unwrap();
+            } else {
+                let mut content = vec![];
+                entry.read_to_end(&mut content).unwrap();
+                std::fs::write(path, content).unwrap();
+            }
+        }
+        assert_same_dir(&generated, &extracted);
+    }
+}
+
+#[test]
+fn cpio_roundtrip() {
+    let tmp = tempfile::tempdir().unwrap();
+    let generated = tmp.path().join("generated");
+    let extracted = tmp.path().join("extracted");
+
+    let tree = build_tree(42);
+    tree.generate(&generated).unwrap();
+    let mut writer = CpioWriter::new(vec![]);
+    tree.export(&mut writer).unwrap();
+    let archive = writer.into_inner();
+
+    std::fs::create_dir(&extracted).unwrap();
+    extract_cpio(&archive, &extracted);
+    assert_same_dir(&generated, &extracted);
+}
@@ -0,0 +1,59 @@
+use arx_test_dir::Pattern;
+use std::path::Path;
+
+#[test]
+fn matches() {
+    let cases: &[(&str, &str, bool)] = &[
+        ("src", "src", true),
+        ("src", "src/lib", false),
+        ("src", "lib", false),
+        ("src", "", false),
+        ("", "", true),
+        ("./src", "src", true),
+        ("src/", "src", true),
+        ("*", "src", true),
+        ("*", "src/lib", false),
+        ("*.rs", "main.rs", true),
+        ("*.rs", "main.rs.bak", false),
+        ("*.rs", ".rs", true),
+        ("m*n.rs", "main.rs", true),
+        ("m*n.rs", "mn.rs", true),
+        ("m*n.rs", "mx.rs", false),
+        ("?", "a", true),
+        ("?", "", false),
+        ("?", "ab", false),
+        ("a?c", "abc", true),
+        ("a?c", "ac", false),
+        ("**", "", true),
+        ("**", "a/b/c", true),
+        ("**/node_modules", "node_modules", true),
+        ("**/node_modules", "a/b/node_modules", true),
+        ("**/node_modules", "a/node_modules/b", false),
+        ("*/node_modules/**", "a/node_modules", true),
+        ("*/node_modules/**", "a/node_modules/b/c", true),
+        ("*/node_modules/**", "node_modules/b", false),
+        ("*/node_modules/**", "a/b/node_modules/c", false),
+        ("a/**/z", "a/z", true),
+        ("a/**/z", "a/b/c/z", true),
+        ("a/**/z", "a/b/c", false),
+        ("**/*.rs", "src/export/tar.rs", true),
+        ("**/*.rs", "src/export", false),
+    ];
+    for (pattern, path, expected) in cases {
+        assert_eq!(
+            Pattern::new(pattern).matches(Path::new(path)),
+            *expected,
+            "{pattern:?} on {path:?}"
+        );
+    }
+}
+
+#[test]
+fn source_is_kept() {
+    for source in ["*/node_modules/**", "./src/", ""] {
+        let pattern = Pattern::new(source);
+        assert_eq!(pattern.as_str(), source);
+        assert_eq!(pattern.to_string(), source);
+        assert_eq!(Pattern::from(String::from(pattern.clone())), pattern);
+    }
+}
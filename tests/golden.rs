@@ -0,0 +1,200 @@
+use arx_test_dir::{
+    Config, ContextBuilder, DirEntry, EntryRef, GenerateOptions, Mutations, Overwrite,
+    ProgressInfo, GENERATOR_VERSION, GENERATOR_VERSIONS,
+};
+use sha2::{Digest, Sha256};
+
+// Hashes of reference trees for each generator version. They must never change:
+// a change of the generated trees needs a new generator version.
+const GOLDEN: &[(u32, &str, &str)] = &[
+    (
+        1,
+        "default",
+        "4f2927c67345625aa364ff6ea6a452e157b2580d0f9fb56c25da38d0dbacc126",
+    ),
+    (
+        1,
+        "tiny",
+        "cd880ee8c1c7e1ad9805ba775ba48e928caae4b2b8e6a7a80f51638141bfb1e3",
+    ),
+    (
+        1,
+        "rules",
+        "40c016a731b863449b6228c189a1f702644b62923689b58c59c1b16233dcc809",
+    ),
+    (
+        1,
+        "mutated",
+        "c324bf6d6adc5793fdc4ad3e1ef6a9470e73aa92586f92a4b131f3c143d30697",
+    ),
+    (
+        2,
+        "default",
+        "79474da5a2b58399c022afa8151da1e7998c14b94c2e56bbc36885136dc2d828",
+    ),
+    (
+        2,
+        "tiny",
+        "706c792721758617e32674799e232a1336474065859e7bc83729eac4b39a3694",
+    ),
+    (
+        2,
+        "rules",
+        "cb1b5d326d807f6a78b2eff764ec178a8a45a9fd36f930821676c2d9e5c28ae0",
+    ),
+    (
+        2,
+        "mutated",
+        "b953e583c5244daf3626f692d25a8bc61d75a4fec88d529c33be06c7017e63b8",
+    ),
+];
+
+fn case(name: &str, version: u32) -> DirEntry {
+    let mut builder = ContextBuilder::new();
+    builder.version(version);
+    match name {
+        "default" => {
+            builder.seed(7).file_len(0..20_000);
+        }
+        "binary" => {
+            builder
+                .seed(7)
+                .nb_file_child(5..20)
+                .file_len(0..20_000)
+                .binary_ratio(1.0);
+        }
+        "tiny" => {
+            Config::preset("tiny").unwrap().apply(&mut builder).unwrap();
+            builder.seed(1);
+        }
+        "rules" => {
+            let config: Config = toml::from_str(
+                r#"
+                seed = 42
+                dir_depth = "2..4"
+                dir_ratio = 0.3
+                file_len = "0..8KiB"
+
+                [[rule]]
+                path = "src/**"
+                binary_ratio = 0.0
+
+                [[rule]]
+                depth = "1"
+                dir_names = ["src"]
+                "#,
+            )
+            .unwrap();
+            config.apply(&mut builder).unwrap();
+        }
+        "mutated" => {
+            builder.seed(3).file_len(0..20_000);
+            let tree = DirEntry::new_root(builder.create().unwrap());
+            return tree.mutate(5, &Mutations::uniform(10)).0;
+        }
+        _ => unreachable!(),
+    }
+    DirEntry::new_root(builder.create().unwrap())
+}
+
+/// Hash of the manifest followed by the hash of each file content.
+fn tree_hash(tree: &DirEntry) -> String {
+    let mut hasher = Sha256::new();
+    tree.write_manifest(&mut hasher).unwrap();
+    for (_, entry) in tree.iter_dfs() {
+        if let EntryRef::File(file) = entry {
+            hasher.update(file.sha256());
+        }
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+#[test]
+fn golden_hashes() {
+    for version in GENERATOR_VERSIONS {
+        assert!(
+            GOLDEN.iter().any(|(v, _, _)| *v == version),
+            "no golden hash for version {version}"
+        );
+    }
+    let mut errors = vec![];
+    for (version, name, expected) in GOLDEN {
+        let actual = tree_hash(&case(name, *version));
+        if &actual != expected {
+            errors.push(format!("version {version}, {name}: got {actual}"));
+        }
+    }
+    assert!(errors.is_empty(), "{}", errors.join("\n"));
+}
+
+#[test]
+fn manifest_keeps_version() {
+    for version in GENERATOR_VERSIONS {
+        let tree = case("default", version);
+        let mut manifest = vec![];
+        tree.write_manifest(&mut manifest).unwrap();
+        let loaded = DirEntry::from_manifest(&mut manifest.as_slice()).unwrap();
+        assert_eq!(tree_hash(&tree), tree_hash(&loaded), "version {version}");
+    }
+}
+
+#[test]
+fn fingerprints() {
+    let tree = case("default", GENERATOR_VERSION);
+    assert_eq!(tree.fingerprint(1), tree.fingerprint(4));
+    assert_ne!(tree.fingerprint(1), tree.structure_fingerprint());
+    let mutated = tree.mutate(1, &Mutations::uniform(1)).0;
+    assert_ne!(tree.fingerprint(1), mutated.fingerprint(1));
+}
+
+#[test]
+fn apply_then_verify() {
+    for version in GENERATOR_VERSIONS {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("tree");
+        let tree = case("binary", version);
+        tree.generate(&dir).unwrap();
+        let (mutated, changes) = tree.mutate(3, &Mutations::uniform(10));
+        mutated.apply(&changes, &dir).unwrap();
+        assert!(
+            mutated.verify(&dir).unwrap().is_empty(),
+            "version {version}"
+        );
+    }
+}
+
+#[test]
+fn resume_then_verify() {
+    for version in GENERATOR_VERSIONS {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("tree");
+        let tree = case("binary", version);
+        tree.generate(&dir).unwrap();
+        // Sizes ending in the middle of a random u64.
+        for (path, entry) in tree.iter_dfs() {
+            if let EntryRef::File(file) = entry {
+                let file_len = file.size() as u64;
+                if file_len > 1006 {
+                    let out = std::fs::OpenOptions::new()
+                        .write(true)
+                        .open(dir.join(path))
+                        .unwrap();
+                    out.set_len(1001 + file_len % 7).unwrap();
+                }
+            }
+        }
+        assert!(!tree.verify(&dir).unwrap().is_empty());
+        let options = GenerateOptions {
+            overwrite: Overwrite::Verify,
+            into_existing: true,
+            ..Default::default()
+        };
+        tree.generate_with(&dir, &options, &mut |_: &ProgressInfo| {})
+            .unwrap();
+        assert!(tree.verify(&dir).unwrap().is_empty(), "version {version}");
+    }
+}
@@ -0,0 +1,152 @@
+// Empty and reversed ranges are parsed and formatted like any other.
+#![allow(clippy::reversed_empty_ranges)]
+
+use arx_test_dir::{
+    format_range, format_size, format_size_range, parse_range, parse_size, parse_size_range,
+};
+
+#[test]
+fn parse_sizes() {
+    let cases: &[(&str, Option<u64>)] = &[
+        ("0", Some(0)),
+        ("42", Some(42)),
+        (" 42 ", Some(42)),
+        ("42B", Some(42)),
+        ("1K", Some(1024)),
+        ("1k", Some(1024)),
+        ("1KiB", Some(1024)),
+        ("1KB", Some(1000)),
+        ("3M", Some(3 << 20)),
+        ("3MiB", Some(3 << 20)),
+        ("3MB", Some(3_000_000)),
+        ("2GiB", Some(2 << 30)),
+        ("2GB", Some(2_000_000_000)),
+        ("1TiB", Some(1 << 40)),
+        ("18446744073709551615", Some(u64::MAX)),
+        ("18446744073709551616", None),
+        ("16777216TiB", None),
+        ("", None),
+        ("K", None),
+        ("1X", None),
+        ("1PiB", None),
+        ("-1", None),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(parse_size(input).ok(), *expected, "{input:?}");
+    }
+}
+
+#[test]
+fn format_sizes() {
+    let cases: &[(u64, &str)] = &[
+        (0, "0"),
+        (1, "1"),
+        (1000, "1KB"),
+        (1024, "1KiB"),
+        (1536, "1536"),
+        (3 << 20, "3MiB"),
+        (3_000_000, "3MB"),
+        (2 << 30, "2GiB"),
+        (1 << 40, "1TiB"),
+        (u64::MAX, "18446744073709551615"),
+    ];
+    for (size, expected) in cases {
+        assert_eq!(format_size(*size), *expected, "{size}");
+    }
+}
+
+#[test]
+fn parse_ranges() {
+    let cases: &[(&str, Option<std::ops::Range<u64>>)] = &[
+        ("5", Some(5..6)),
+        ("2..5", Some(2..5)),
+        ("2..=5", Some(2..6)),
+        ("5..2", Some(5..2)),
+        ("0..18446744073709551615", Some(0..u64::MAX)),
+        ("0..=18446744073709551614", Some(0..u64::MAX)),
+        ("0..=18446744073709551615", None),
+        ("18446744073709551615", None),
+        ("1K", None),
+        ("a..b", None),
+        ("", None),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(parse_range(input).ok(), *expected, "{input:?}");
+    }
+}
+
+#[test]
+fn parse_size_ranges() {
+    let cases: &[(&str, Option<std::ops::Range<usize>>)] = &[
+        ("4K", Some(4096..4097)),
+        ("10..1KB", Some(10..1000)),
+        ("4KiB..=2GiB", Some(4096..(2 << 30) + 1)),
+        ("1K..2KB", Some(1024..2000)),
+        ("0..=18446744073709551615", None),
+        ("1X..2", None),
+    ];
+    for (input, expected) in cases {
+        assert_eq!(parse_size_range(input).ok(), *expected, "{input:?}");
+    }
+}
+
+#[test]
+fn format_ranges() {
+    let cases: &[(std::ops::Range<u64>, &str)] = &[
+        (5..6, "5"),
+        (2..5, "2..5"),
+        (5..2, "5..2"),
+        (0..u64::MAX, "0..18446744073709551615"),
+        (
+            u64::MAX..u64::MAX,
+            "18446744073709551615..18446744073709551615",
+        ),
+    ];
+    for (range, expected) in cases {
+        assert_eq!(format_range(range), *expected, "{range:?}");
+    }
+}
+
+#[test]
+fn format_size_ranges() {
+    let cases: &[(std::ops::Range<usize>, &str)] = &[
+        (4096..4097, "4KiB"),
+        (10..1000, "10..1KB"),
+        (4096..(2 << 30) + 1, "4KiB..=2GiB"),
+        (0..1024, "0..1KiB"),
+        (1023..1025, "1023..=1KiB"),
+        (usize::MAX..5, "18446744073709551615..5"),
+        (
+            usize::MAX..usize::MAX,
+            "18446744073709551615..18446744073709551615",
+        ),
+    ];
+    for (range, expected) in cases {
+        assert_eq!(format_size_range(range), *expected, "{range:?}");
+    }
+}
+
+#[test]
+fn round_trips() {
+    for input in ["5", "2..5", "2..=5", "0..18446744073709551615", "7..7"] {
+        let range = parse_range(input).unwrap();
+        assert_eq!(parse_range(&format_range(&range)), Ok(range), "{input:?}");
+    }
+    for input in [
+        "4K",
+        "10..1KB",
+        "4KiB..=2GiB",
+        "1K..2KB",
+        "0..1000000",
+        "100MB..1GB",
+        "18446744073709551615..5",
+    ] {
+        let range = parse_size_range(input).unwrap();
+        let formatted = format_size_range(&range);
+        assert_eq!(
+            parse_size_range(&formatted),
+            Ok(range),
+            "{input:?} -> {formatted:?}"
+        );
+    }
+}